@@ -0,0 +1,122 @@
+use std::{error::Error, fmt};
+
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatcherError {
+    UnknownPair(String),
+    PairAlreadyExists(String),
+    PairInactive(String),
+    UnknownOrder(String),
+    OrderNotCancellable(String),
+    OrderAlreadyCancelled(String),
+    OrderAlreadyExecuted(String),
+    MissingLimitPrice,
+    InvalidQuantity,
+    QuantityPrecision { max_scale: u32 },
+    InvalidExpiry(i64),
+    PostOnlyRequiresLimit,
+    InvalidStopPrice(Decimal),
+    OrderNotAmendable(String),
+    InvalidTrailingOffset,
+    InvalidDisplayQuantity,
+    HiddenRequiresLimit,
+    InvalidPeg,
+    PegUnavailable,
+    UnknownGroup(String),
+    OcoLegsCross,
+    InvalidBracket,
+    NotAllowedInAuction,
+    TradingHalted,
+    InvalidRules,
+    PriceNotOnTick { price: Decimal, tick_size: Decimal },
+    QuantityNotOnLot { lot_size: Decimal },
+    QuantityBelowMinimum(Decimal),
+    QuantityAboveMaximum(Decimal),
+    NotionalBelowMinimum(Decimal),
+    QuoteBudgetUnfillable(Decimal),
+}
+
+impl fmt::Display for MatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatcherError::UnknownPair(pair_id) => write!(f, "Invalid pair id: {pair_id}"),
+            MatcherError::PairAlreadyExists(pair_id) => write!(f, "Pair already exists: {pair_id}"),
+            MatcherError::PairInactive(pair_id) => write!(f, "Pair is not active: {pair_id}"),
+            MatcherError::UnknownOrder(order_id) => write!(f, "Invalid order id: {order_id}"),
+            MatcherError::OrderNotCancellable(order_id) => {
+                write!(f, "Order can't be cancelled: {order_id}")
+            }
+            MatcherError::OrderAlreadyCancelled(order_id) => {
+                write!(f, "Order already cancelled: {order_id}")
+            }
+            MatcherError::OrderAlreadyExecuted(order_id) => {
+                write!(f, "Order already executed: {order_id}")
+            }
+            MatcherError::MissingLimitPrice => write!(f, "Limit order needs a price"),
+            MatcherError::InvalidQuantity => write!(f, "Quantity must be positive"),
+            MatcherError::QuantityPrecision { max_scale } => {
+                write!(f, "Quantity can have at most {max_scale} decimal places")
+            }
+            MatcherError::InvalidExpiry(expiry) => {
+                write!(f, "Expiry must be in the future: {expiry}")
+            }
+            MatcherError::PostOnlyRequiresLimit => {
+                write!(f, "Post-only orders must be limit orders")
+            }
+            MatcherError::InvalidStopPrice(stop_price) => {
+                write!(f, "Stop price must be positive: {stop_price}")
+            }
+            MatcherError::OrderNotAmendable(order_id) => {
+                write!(f, "Order can't be amended: {order_id}")
+            }
+            MatcherError::InvalidTrailingOffset => write!(f, "Trailing offset must be positive"),
+            MatcherError::InvalidDisplayQuantity => {
+                write!(f, "Display quantity must be positive and on a limit order")
+            }
+            MatcherError::HiddenRequiresLimit => write!(f, "Hidden orders must be limit orders"),
+            MatcherError::InvalidPeg => {
+                write!(
+                    f,
+                    "Peg offset can't be negative and the cap must be positive"
+                )
+            }
+            MatcherError::PegUnavailable => write!(f, "No top of book price to peg to"),
+            MatcherError::UnknownGroup(group_id) => write!(f, "Invalid group id: {group_id}"),
+            MatcherError::OcoLegsCross => {
+                write!(f, "One-cancels-other legs would trade with each other")
+            }
+            MatcherError::InvalidBracket => {
+                write!(
+                    f,
+                    "Take profit and stop loss must be positive and on either side of the entry"
+                )
+            }
+            MatcherError::NotAllowedInAuction => {
+                write!(f, "Market, IOC and FOK orders can't enter an auction")
+            }
+            MatcherError::TradingHalted => write!(f, "Trading is halted"),
+            MatcherError::InvalidRules => write!(f, "Instrument rules are inconsistent"),
+            MatcherError::PriceNotOnTick { price, tick_size } => {
+                write!(f, "Price {price} is not a multiple of the {tick_size} tick")
+            }
+            MatcherError::QuantityNotOnLot { lot_size } => {
+                write!(f, "Quantity must be a multiple of the {lot_size} lot")
+            }
+            MatcherError::QuantityBelowMinimum(min_quantity) => {
+                write!(f, "Quantity is below the minimum of {min_quantity}")
+            }
+            MatcherError::QuantityAboveMaximum(max_quantity) => {
+                write!(f, "Quantity is above the maximum of {max_quantity}")
+            }
+            MatcherError::NotionalBelowMinimum(min_notional) => {
+                write!(f, "Notional is below the minimum of {min_notional}")
+            }
+            MatcherError::QuoteBudgetUnfillable(quote_budget) => {
+                write!(f, "Quote budget of {quote_budget} can't fill a single lot")
+            }
+        }
+    }
+}
+
+impl Error for MatcherError {}
@@ -1,7 +1,22 @@
 use core::fmt;
-use std::{collections::HashMap, fmt::format};
+use std::collections::HashMap;
 
-use crate::orderbook::{order::OrderType, orderbook::OrderBook};
+use chrono::Utc;
+use rust_decimal::Decimal;
+
+use crate::error::MatcherError;
+use crate::orderbook::{
+    allocation::AllocationPolicy,
+    auction::{IndicativeUncross, TradingPhase},
+    band::PriceBand,
+    depth::Depth,
+    history::OrderEvent,
+    order::{CancelFilter, OrderKind, OrderOptions, OrderRequest, OrderStatus, Side},
+    orderbook::OrderBook,
+    report::{ExecutionReport, OcoReport, PegAmend, UncrossReport},
+    rules::InstrumentRules,
+    ticker::Ticker,
+};
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub struct TradingPair {
@@ -9,18 +24,33 @@ pub struct TradingPair {
     base: String,
     quote: String,
     is_active: bool,
-    listing_price: String,
+    listing_price: Decimal,
+    // number of decimal places a quantity may carry
+    quantity_scale: u32,
+    allocation_policy: AllocationPolicy,
+    // circuit breaker around the last traded price, which starts out as the listing price
+    price_band: Option<PriceBand>,
+    rules: InstrumentRules,
 }
 
 impl TradingPair {
-    pub fn new(base: String, quote: String, listing_price: f64) -> TradingPair {
+    pub fn new(
+        base: String,
+        quote: String,
+        listing_price: Decimal,
+        quantity_scale: u32,
+    ) -> TradingPair {
         let id = format!("{base}{quote}");
         TradingPair {
             id,
             base,
             quote,
             is_active: true,
-            listing_price: listing_price.to_string(),
+            listing_price,
+            quantity_scale,
+            allocation_policy: AllocationPolicy::default(),
+            price_band: None,
+            rules: InstrumentRules::default(),
         }
     }
 }
@@ -35,32 +65,115 @@ impl fmt::Display for TradingPair {
     }
 }
 
-#[derive(Debug)]
+// take-profit and stop-loss pair protecting one filled slice of a bracket entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketLegs {
+    pub group_id: String,
+    pub take_profit_id: String,
+    pub stop_loss_id: String,
+    pub quantity: Decimal,
+}
+
+// an entry order whose fills are protected by one-cancels-other take-profit and stop-loss
+// pairs, one pair per slice of the entry that filled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bracket {
+    pub pair_id: String,
+    pub entry_id: String,
+    pub side: Side,
+    pub take_profit_price: Decimal,
+    pub stop_loss_price: Decimal,
+    // children trade for the entry's owner
+    pub owner: Option<String>,
+    // entry quantity that can still fill, and how much of it children already protect
+    pub entry_quantity: Decimal,
+    pub activated_quantity: Decimal,
+    pub entry_open: bool,
+    pub children: Vec<BracketLegs>,
+    // why the last try at protecting filled quantity failed, it is retried on every change to
+    // the pair until it succeeds
+    pub activation_error: Option<MatcherError>,
+}
+
+impl Bracket {
+    fn activate(
+        &mut self,
+        book: &mut OrderBook,
+        quantity: Decimal,
+    ) -> Result<BracketLegs, MatcherError> {
+        let side = self.side.opposite();
+        let oco = book.add_oco_orders(
+            OrderRequest {
+                side,
+                kind: OrderKind::Limit,
+                price: Some(self.take_profit_price),
+                quantity,
+                options: OrderOptions {
+                    owner: self.owner.clone(),
+                    ..Default::default()
+                },
+            },
+            OrderRequest {
+                side,
+                kind: OrderKind::Stop {
+                    stop_price: self.stop_loss_price,
+                },
+                price: None,
+                quantity,
+                options: OrderOptions {
+                    owner: self.owner.clone(),
+                    ..Default::default()
+                },
+            },
+        )?;
+
+        let legs = BracketLegs {
+            group_id: oco.group_id,
+            take_profit_id: oco.first.order_id,
+            stop_loss_id: oco.second.order_id,
+            quantity,
+        };
+        self.activated_quantity += quantity;
+        self.children.push(legs.clone());
+        Ok(legs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketReport {
+    pub entry: ExecutionReport,
+    // children the entry's immediate fills activated
+    pub children: Vec<BracketLegs>,
+    // why fills the entry made could not be protected yet
+    pub activation_error: Option<MatcherError>,
+}
+
+#[derive(Debug, Default)]
 pub struct Matcher {
     pub books: HashMap<String, OrderBook>,
     pub pairs: HashMap<String, TradingPair>,
+    // bracket orders keyed by entry order id
+    brackets: HashMap<String, Bracket>,
 }
 
 impl Matcher {
     pub fn new() -> Matcher {
-        Matcher {
-            books: HashMap::new(),
-            pairs: HashMap::new(),
-        }
+        Matcher::default()
     }
 
     pub fn add_pair(
         &mut self,
         base: String,
         quote: String,
-        listing_price: f64,
-    ) -> Result<String, String> {
-        let pair = TradingPair::new(base, quote, listing_price);
+        listing_price: Decimal,
+        quantity_scale: u32,
+    ) -> Result<String, MatcherError> {
+        let pair = TradingPair::new(base, quote, listing_price, quantity_scale);
         let id = pair.id.clone();
         match self.books.get(&id) {
-            Some(_) => Err(format!("Pair already exits ")),
+            Some(_) => Err(MatcherError::PairAlreadyExists(id)),
             None => {
-                let order_book = OrderBook::new(id.clone(), listing_price);
+                let order_book = OrderBook::new(id.clone(), listing_price, quantity_scale);
                 self.books.insert(id.clone(), order_book);
 
                 self.pairs.insert(id.clone(), pair);
@@ -71,68 +184,486 @@ impl Matcher {
         }
     }
 
-    pub fn get_pair(&self, pair_id: String) -> Result<&TradingPair, String> {
+    pub fn get_pair(&self, pair_id: String) -> Result<&TradingPair, MatcherError> {
         match self.pairs.get(&pair_id) {
-            Some(pair) => return Ok(&pair),
-            None => Err("Invalid pair id".to_string()),
+            Some(pair) => Ok(pair),
+            None => Err(MatcherError::UnknownPair(pair_id)),
         }
     }
 
-    pub fn update_pool(&mut self, pair_id: String, enable: bool) -> Result<(), String> {
+    pub fn update_pool(&mut self, pair_id: String, enable: bool) -> Result<(), MatcherError> {
         match self.pairs.get_mut(&pair_id) {
             Some(pair) => {
                 pair.is_active = enable;
                 Ok(())
             }
-            None => Err("Invalid pair id".to_string()),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn set_allocation_policy(
+        &mut self,
+        pair_id: String,
+        allocation_policy: AllocationPolicy,
+    ) -> Result<(), MatcherError> {
+        match (self.pairs.get_mut(&pair_id), self.books.get_mut(&pair_id)) {
+            (Some(pair), Some(book)) => {
+                pair.allocation_policy = allocation_policy;
+                book.set_allocation_policy(allocation_policy);
+                Ok(())
+            }
+            _ => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn set_rules(
+        &mut self,
+        pair_id: String,
+        rules: InstrumentRules,
+    ) -> Result<(), MatcherError> {
+        if !rules.is_valid() {
+            return Err(MatcherError::InvalidRules);
+        }
+
+        match (self.pairs.get_mut(&pair_id), self.books.get_mut(&pair_id)) {
+            (Some(pair), Some(book)) => {
+                book.set_rules(rules.clone());
+                pair.rules = rules;
+                Ok(())
+            }
+            _ => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn set_price_band(
+        &mut self,
+        pair_id: String,
+        price_band: Option<PriceBand>,
+    ) -> Result<(), MatcherError> {
+        match (self.pairs.get_mut(&pair_id), self.books.get_mut(&pair_id)) {
+            (Some(pair), Some(book)) => {
+                pair.price_band = price_band;
+                book.set_price_band(price_band);
+                Ok(())
+            }
+            _ => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    fn check_active(&self, pair_id: &String) -> Result<(), MatcherError> {
+        match self.pairs.get(pair_id) {
+            Some(pair) if pair.is_active => Ok(()),
+            Some(_) => Err(MatcherError::PairInactive(pair_id.clone())),
+            None => Err(MatcherError::UnknownPair(pair_id.clone())),
         }
     }
 
     pub fn add_order(
         &mut self,
         pair_id: String,
-        order_type: OrderType,
-        price: Option<f64>,
-        quantity: f64,
-    ) -> Result<String, String> {
+        side: Side,
+        kind: OrderKind,
+        price: Option<Decimal>,
+        quantity: Decimal,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.add_order_with_options(
+            pair_id,
+            side,
+            kind,
+            price,
+            quantity,
+            OrderOptions::default(),
+        )
+    }
+
+    pub fn add_order_with_options(
+        &mut self,
+        pair_id: String,
+        side: Side,
+        kind: OrderKind,
+        price: Option<Decimal>,
+        quantity: Decimal,
+        options: OrderOptions,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.check_active(&pair_id)?;
+
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.add_order_with_options(side, kind, price, quantity, options),
+            None => Err(MatcherError::UnknownPair(pair_id.clone())),
+        };
+        self.sync_brackets(&pair_id);
+        report
+    }
+
+    pub fn add_quote_market_order(
+        &mut self,
+        pair_id: String,
+        side: Side,
+        quote_budget: Decimal,
+        options: OrderOptions,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.check_active(&pair_id)?;
+
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.add_quote_market_order(side, quote_budget, options),
+            None => Err(MatcherError::UnknownPair(pair_id.clone())),
+        };
+        self.sync_brackets(&pair_id);
+        report
+    }
+
+    // enters the entry order and, as it fills, a take-profit limit and a stop-loss on the
+    // opposite side for each filled slice
+    pub fn add_bracket_order(
+        &mut self,
+        pair_id: String,
+        entry: OrderRequest,
+        take_profit_price: Decimal,
+        stop_loss_price: Decimal,
+    ) -> Result<BracketReport, MatcherError> {
+        self.check_active(&pair_id)?;
+
+        let valid = take_profit_price > Decimal::ZERO
+            && stop_loss_price > Decimal::ZERO
+            && match entry.side {
+                Side::Buy => take_profit_price > stop_loss_price,
+                Side::Sell => take_profit_price < stop_loss_price,
+            };
+        if !valid {
+            return Err(MatcherError::InvalidBracket);
+        }
+
+        let owner = entry.options.owner.clone();
+        let book = self
+            .books
+            .get_mut(&pair_id)
+            .ok_or_else(|| MatcherError::UnknownPair(pair_id.clone()))?;
+        let report = book.add_order_with_options(
+            entry.side,
+            entry.kind,
+            entry.price,
+            entry.quantity,
+            entry.options,
+        )?;
+
+        // a rejected entry never trades, there is nothing to protect
+        if report.status == OrderStatus::Rejected {
+            return Ok(BracketReport {
+                entry: report,
+                children: Vec::new(),
+                activation_error: None,
+            });
+        }
+
+        let entry_id = report.order_id.clone();
+        self.brackets.insert(
+            entry_id.clone(),
+            Bracket {
+                pair_id: pair_id.clone(),
+                entry_id: entry_id.clone(),
+                side: entry.side,
+                take_profit_price,
+                stop_loss_price,
+                owner,
+                entry_quantity: entry.quantity,
+                activated_quantity: Decimal::ZERO,
+                entry_open: true,
+                children: Vec::new(),
+                activation_error: None,
+            },
+        );
+        self.sync_brackets(&pair_id);
+
+        let bracket = &self.brackets[&entry_id];
+        Ok(BracketReport {
+            entry: report,
+            children: bracket.children.clone(),
+            activation_error: bracket.activation_error.clone(),
+        })
+    }
+
+    pub fn get_bracket(&self, entry_id: String) -> Option<&Bracket> {
+        self.brackets.get(&entry_id)
+    }
+
+    // cancels what is left of the entry and every child pair still live
+    pub fn cancel_bracket(&mut self, entry_id: String) -> Result<(), MatcherError> {
+        let bracket = self
+            .brackets
+            .remove(&entry_id)
+            .ok_or_else(|| MatcherError::UnknownOrder(entry_id.clone()))?;
+        let Some(book) = self.books.get_mut(&bracket.pair_id) else {
+            return Ok(());
+        };
+
+        if bracket.entry_open {
+            let _ = book.cancel_order(entry_id);
+        }
+        // children that already ran their course are no longer grouped
+        for legs in bracket.children {
+            let _ = book.cancel_oco_group(legs.group_id);
+        }
+        Ok(())
+    }
+
+    // activates children for whatever bracket entries on the pair filled and are not protected
+    // yet, until activating children fills no further entries; a failed activation is kept on
+    // the bracket and tried again next time
+    fn sync_brackets(&mut self, pair_id: &String) {
+        let Some(book) = self.books.get_mut(pair_id) else {
+            return;
+        };
+
+        loop {
+            let mut activated = false;
+            let brackets = self
+                .brackets
+                .values_mut()
+                .filter(|bracket| bracket.pair_id == *pair_id);
+
+            for bracket in brackets {
+                // only trades count as filled, however the entry left the book
+                let filled: Decimal = book
+                    .order_history(bracket.entry_id.clone())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|event| event.traded_quantity)
+                    .sum();
+                bracket.entry_open =
+                    book.get_order(bracket.entry_id.clone())
+                        .is_some_and(|entry| {
+                            matches!(
+                                entry.status(),
+                                OrderStatus::Open | OrderStatus::PartiallyExecuted
+                            )
+                        });
+
+                let quantity = filled - bracket.activated_quantity;
+                if quantity > Decimal::ZERO {
+                    match bracket.activate(book, quantity) {
+                        Ok(_) => {
+                            bracket.activation_error = None;
+                            activated = true;
+                        }
+                        Err(error) => bracket.activation_error = Some(error),
+                    }
+                }
+            }
+
+            if !activated {
+                return;
+            }
+        }
+    }
+
+    pub fn add_oco_orders(
+        &mut self,
+        pair_id: String,
+        first: OrderRequest,
+        second: OrderRequest,
+    ) -> Result<OcoReport, MatcherError> {
+        self.check_active(&pair_id)?;
+
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.add_oco_orders(first, second),
+            None => Err(MatcherError::UnknownPair(pair_id.clone())),
+        };
+        self.sync_brackets(&pair_id);
+        report
+    }
+
+    pub fn cancel_oco_group(&mut self, group_id: String) -> Result<(), MatcherError> {
+        let pair_id = group_id.split('-').next().unwrap().to_string();
+
+        let result = match self.books.get_mut(&pair_id) {
+            Some(book) => book.cancel_oco_group(group_id),
+            None => Err(MatcherError::UnknownGroup(group_id)),
+        };
+        self.sync_brackets(&pair_id);
+        result
+    }
+
+    // moves the pair into a call auction, for the open or the close
+    pub fn start_auction(&mut self, pair_id: String) -> Result<(), MatcherError> {
         match self.books.get_mut(&pair_id) {
             Some(book) => {
-                return book.add_order(order_type, price, quantity);
-            }
-            None => {
-                return Err("Invalid PoolId".to_owned());
+                book.start_auction();
+                Ok(())
             }
+            None => Err(MatcherError::UnknownPair(pair_id)),
         }
     }
 
-    pub fn cancel_order(&mut self, order_id: String) -> Result<(), String> {
-        let pair_id = order_id.split('-').next().unwrap().to_string();
+    // uncrosses the pair's auction and returns it to continuous trading
+    pub fn uncross(&mut self, pair_id: String) -> Result<UncrossReport, MatcherError> {
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.uncross(),
+            None => return Err(MatcherError::UnknownPair(pair_id)),
+        };
+        self.sync_brackets(&pair_id);
+        Ok(report)
+    }
+
+    // reopens the pair once its circuit breaker period has run out
+    pub fn resume_trading(
+        &mut self,
+        pair_id: String,
+    ) -> Result<Option<UncrossReport>, MatcherError> {
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.resume_trading(Utc::now().timestamp()),
+            None => return Err(MatcherError::UnknownPair(pair_id)),
+        };
+        self.sync_brackets(&pair_id);
+        Ok(report)
+    }
+
+    pub fn phase(&self, pair_id: String) -> Result<TradingPhase, MatcherError> {
+        match self.books.get(&pair_id) {
+            Some(book) => Ok(book.phase()),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn indicative_uncross(
+        &self,
+        pair_id: String,
+    ) -> Result<Option<IndicativeUncross>, MatcherError> {
+        match self.books.get(&pair_id) {
+            Some(book) => Ok(book.indicative_uncross()),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    // closes the trading session on every book, returning the ids of expired DAY orders
+    pub fn end_session(&mut self) -> Vec<String> {
+        let expired = self
+            .books
+            .values_mut()
+            .flat_map(|book| book.end_session())
+            .collect();
 
+        let pair_ids: Vec<String> = self.books.keys().cloned().collect();
+        for pair_id in pair_ids.iter() {
+            self.sync_brackets(pair_id);
+        }
+        expired
+    }
+
+    pub fn depth(&self, pair_id: String, levels: usize) -> Result<Depth, MatcherError> {
+        match self.books.get(&pair_id) {
+            Some(book) => Ok(book.depth(levels)),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn ticker(&self, pair_id: String) -> Result<Ticker, MatcherError> {
+        match self.books.get(&pair_id) {
+            Some(book) => Ok(book.ticker()),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn drain_peg_amends(&mut self, pair_id: String) -> Result<Vec<PegAmend>, MatcherError> {
         match self.books.get_mut(&pair_id) {
-            Some(book) => book.cancel_order(order_id),
-            None => Err("Invalid Order Id".to_string()),
+            Some(book) => Ok(book.drain_peg_amends()),
+            None => Err(MatcherError::UnknownPair(pair_id)),
+        }
+    }
+
+    pub fn order_history(&self, order_id: String) -> Result<Vec<OrderEvent>, MatcherError> {
+        let pair_id = order_id.split('-').next().unwrap().to_string();
+
+        self.books
+            .get(&pair_id)
+            .and_then(|book| book.order_history(order_id.clone()))
+            .map(|events| events.to_vec())
+            .ok_or(MatcherError::UnknownOrder(order_id))
+    }
+
+    pub fn cancel_order(&mut self, order_id: String) -> Result<(), MatcherError> {
+        let pair_id = order_id.split('-').next().unwrap().to_string();
+
+        // children cover every fill up to the cancel, nothing after it
+        self.sync_brackets(&pair_id);
+        let result = match self.books.get_mut(&pair_id) {
+            Some(book) => book.cancel_order(order_id.clone()),
+            None => Err(MatcherError::UnknownOrder(order_id.clone())),
+        };
+        if result.is_ok() {
+            if let Some(bracket) = self.brackets.get_mut(&order_id) {
+                bracket.entry_open = false;
+            }
+        }
+        self.sync_brackets(&pair_id);
+        result
+    }
+
+    // cancels the open orders the filter selects on one pair, or on every pair without one
+    pub fn mass_cancel(
+        &mut self,
+        pair_id: Option<String>,
+        filter: CancelFilter,
+    ) -> Result<Vec<String>, MatcherError> {
+        let pair_ids: Vec<String> = match pair_id {
+            Some(pair_id) if !self.books.contains_key(&pair_id) => {
+                return Err(MatcherError::UnknownPair(pair_id))
+            }
+            Some(pair_id) => vec![pair_id],
+            None => self.books.keys().cloned().collect(),
+        };
+
+        let mut cancelled = Vec::new();
+        for pair_id in pair_ids.iter() {
+            self.sync_brackets(pair_id);
+            let book = self.books.get_mut(pair_id).unwrap();
+            cancelled.extend(book.mass_cancel(&filter));
+        }
+
+        for order_id in cancelled.iter() {
+            if let Some(bracket) = self.brackets.get_mut(order_id) {
+                bracket.entry_open = false;
+            }
         }
+        for pair_id in pair_ids.iter() {
+            self.sync_brackets(pair_id);
+        }
+        Ok(cancelled)
     }
 
     pub fn update_order(
         &mut self,
         order_id: String,
-        order_type: Option<OrderType>,
-        price: Option<f64>,
-        quantity: Option<f64>,
-    ) -> Result<(), String> {
+        kind: Option<OrderKind>,
+        price: Option<Decimal>,
+        quantity: Option<Decimal>,
+    ) -> Result<ExecutionReport, MatcherError> {
         let pair_id = order_id.split('-').next().unwrap().to_string();
+        if !self.pairs.contains_key(&pair_id) {
+            return Err(MatcherError::UnknownOrder(order_id));
+        }
+        self.check_active(&pair_id)?;
 
-        match self.books.get_mut(&pair_id) {
-            Some(book) => book.update_order(order_id, quantity, order_type, price),
-            None => Err("Invalid Order Id".to_string()),
+        self.sync_brackets(&pair_id);
+        let report = match self.books.get_mut(&pair_id) {
+            Some(book) => book.update_order(order_id.clone(), quantity, kind, price),
+            None => Err(MatcherError::UnknownOrder(order_id.clone())),
+        };
+
+        // an amended entry can fill up to its new quantity on top of what already filled
+        if let (Ok(report), Some(bracket)) = (&report, self.brackets.get_mut(&order_id)) {
+            bracket.entry_quantity =
+                bracket.activated_quantity + report.filled_quantity + report.remaining_quantity;
         }
+        self.sync_brackets(&pair_id);
+        report
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::orderbook::band::BreachAction;
+    use rust_decimal_macros::dec;
 
     #[test]
     pub fn pass_add_pair() {
@@ -141,16 +672,366 @@ mod tests {
         let base = String::from("ETH");
         let quote = String::from("INC");
 
-        let list_price = 1122345.0;
+        let list_price = dec!(1122345);
 
         let pair_id = matcher
-            .add_pair(base.clone(), quote.clone(), list_price)
+            .add_pair(base.clone(), quote.clone(), list_price, 8)
             .expect("Adding pair failed");
 
         let pair = matcher.get_pair(pair_id).expect("Can't find pair");
 
         assert_eq!(pair.base, base);
         assert_eq!(pair.quote, quote);
-        assert_eq!(pair.listing_price, list_price.to_string());
+        assert_eq!(pair.listing_price, list_price);
+    }
+
+    #[test]
+    pub fn fail_orders_on_unknown_or_inactive_pair() {
+        let mut matcher = Matcher::new();
+
+        let pair_id = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+
+        assert_eq!(
+            matcher.add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8),
+            Err(MatcherError::PairAlreadyExists(pair_id.clone()))
+        );
+
+        assert_eq!(
+            matcher
+                .add_order(
+                    String::from("BTCINC"),
+                    Side::Buy,
+                    OrderKind::Limit,
+                    Some(dec!(100)),
+                    dec!(1)
+                )
+                .unwrap_err(),
+            MatcherError::UnknownPair(String::from("BTCINC"))
+        );
+
+        assert_eq!(
+            matcher
+                .add_order(pair_id.clone(), Side::Buy, OrderKind::Limit, None, dec!(1))
+                .unwrap_err(),
+            MatcherError::MissingLimitPrice
+        );
+
+        matcher
+            .update_pool(pair_id.clone(), false)
+            .expect("Can't disable pair");
+        assert_eq!(
+            matcher
+                .add_order(
+                    pair_id.clone(),
+                    Side::Buy,
+                    OrderKind::Limit,
+                    Some(dec!(100)),
+                    dec!(1)
+                )
+                .unwrap_err(),
+            MatcherError::PairInactive(pair_id)
+        );
+
+        assert_eq!(
+            matcher.cancel_order(String::from("BTCINC-0-000000")),
+            Err(MatcherError::UnknownOrder(String::from("BTCINC-0-000000")))
+        );
+    }
+
+    #[test]
+    pub fn pass_bracket_children_follow_entry_fills() {
+        let mut matcher = Matcher::new();
+
+        let pair_id = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+
+        let entry = OrderRequest {
+            side: Side::Buy,
+            kind: OrderKind::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(3),
+            options: OrderOptions::default(),
+        };
+        assert_eq!(
+            matcher
+                .add_bracket_order(pair_id.clone(), entry.clone(), dec!(90), dec!(110))
+                .unwrap_err(),
+            MatcherError::InvalidBracket
+        );
+
+        let bracket = matcher
+            .add_bracket_order(pair_id.clone(), entry, dec!(110), dec!(90))
+            .expect("Can't add bracket");
+        assert!(bracket.children.is_empty());
+        let entry_id = bracket.entry.order_id;
+
+        // each partial fill of the entry gets its own protection
+        for quantity in [dec!(1), dec!(2)] {
+            matcher
+                .add_order(
+                    pair_id.clone(),
+                    Side::Sell,
+                    OrderKind::Limit,
+                    Some(dec!(100)),
+                    quantity,
+                )
+                .expect("Can't add sell");
+        }
+        let children = matcher
+            .get_bracket(entry_id.clone())
+            .unwrap()
+            .children
+            .clone();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].quantity, dec!(1));
+        assert_eq!(children[1].quantity, dec!(2));
+        assert_eq!(matcher.books[&pair_id].sell_volume, dec!(3));
+
+        // taking profit on the first slice cancels its stop loss only
+        matcher
+            .add_order(
+                pair_id.clone(),
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(110)),
+                dec!(1),
+            )
+            .expect("Can't add buy");
+        let book = &matcher.books[&pair_id];
+        assert_eq!(
+            *book
+                .get_order(children[0].stop_loss_id.clone())
+                .unwrap()
+                .status(),
+            OrderStatus::Cancelled
+        );
+        assert_eq!(
+            *book
+                .get_order(children[1].stop_loss_id.clone())
+                .unwrap()
+                .status(),
+            OrderStatus::Open
+        );
+
+        matcher
+            .cancel_bracket(entry_id.clone())
+            .expect("Can't cancel bracket");
+        assert_eq!(matcher.books[&pair_id].sell_volume, Decimal::ZERO);
+        assert!(matcher.get_bracket(entry_id).is_none());
+    }
+
+    #[test]
+    pub fn fail_orders_breaking_instrument_rules() {
+        let mut matcher = Matcher::new();
+
+        let pair_id = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+
+        assert_eq!(
+            matcher.set_rules(
+                pair_id.clone(),
+                InstrumentRules {
+                    tick_size: Some(Decimal::ZERO),
+                    ..Default::default()
+                }
+            ),
+            Err(MatcherError::InvalidRules)
+        );
+        matcher
+            .set_rules(
+                pair_id.clone(),
+                InstrumentRules {
+                    tick_size: Some(dec!(0.5)),
+                    lot_size: Some(dec!(0.1)),
+                    min_notional: Some(dec!(20)),
+                    ..Default::default()
+                },
+            )
+            .expect("Can't set rules");
+
+        let mut add = |price, quantity| {
+            matcher.add_order(
+                pair_id.clone(),
+                Side::Buy,
+                OrderKind::Limit,
+                Some(price),
+                quantity,
+            )
+        };
+        assert_eq!(
+            add(dec!(99.7), dec!(1)).unwrap_err(),
+            MatcherError::PriceNotOnTick {
+                price: dec!(99.7),
+                tick_size: dec!(0.5)
+            }
+        );
+        assert_eq!(
+            add(dec!(99.5), dec!(1.05)).unwrap_err(),
+            MatcherError::QuantityNotOnLot {
+                lot_size: dec!(0.1)
+            }
+        );
+        assert_eq!(
+            add(dec!(99.5), dec!(0.1)).unwrap_err(),
+            MatcherError::NotionalBelowMinimum(dec!(20))
+        );
+        let order_id = add(dec!(99.5), dec!(1)).expect("Can't add buy").order_id;
+
+        assert_eq!(
+            matcher
+                .update_order(order_id, None, None, Some(dec!(0.15)))
+                .unwrap_err(),
+            MatcherError::QuantityNotOnLot {
+                lot_size: dec!(0.1)
+            }
+        );
+    }
+
+    #[test]
+    pub fn pass_mass_cancel_across_pairs() {
+        let mut matcher = Matcher::new();
+
+        let eth = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+        let btc = matcher
+            .add_pair(String::from("BTC"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+
+        for pair_id in [&eth, &btc] {
+            matcher
+                .add_order(
+                    pair_id.clone(),
+                    Side::Sell,
+                    OrderKind::Limit,
+                    Some(dec!(101)),
+                    dec!(1),
+                )
+                .expect("can't add limit sell");
+        }
+
+        assert_eq!(
+            matcher.mass_cancel(Some(String::from("XRPINC")), CancelFilter::default()),
+            Err(MatcherError::UnknownPair(String::from("XRPINC")))
+        );
+
+        let cancelled = matcher
+            .mass_cancel(Some(eth.clone()), CancelFilter::default())
+            .expect("can't mass cancel");
+        assert_eq!(cancelled.len(), 1);
+        assert!(matcher.depth(eth, 5).unwrap().asks.is_empty());
+        assert_eq!(matcher.depth(btc.clone(), 5).unwrap().asks.len(), 1);
+
+        let cancelled = matcher
+            .mass_cancel(None, CancelFilter::default())
+            .expect("can't mass cancel");
+        assert_eq!(cancelled.len(), 1);
+        assert!(matcher.depth(btc, 5).unwrap().asks.is_empty());
+    }
+
+    #[test]
+    pub fn pass_bracket_ignores_entry_leaving_unfilled() {
+        let mut matcher = Matcher::new();
+
+        let pair_id = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+
+        let entry_id = matcher
+            .add_bracket_order(
+                pair_id.clone(),
+                OrderRequest {
+                    side: Side::Buy,
+                    kind: OrderKind::Limit,
+                    price: Some(dec!(99)),
+                    quantity: dec!(2),
+                    options: OrderOptions::default(),
+                },
+                dec!(110),
+                dec!(90),
+            )
+            .expect("Can't add bracket")
+            .entry
+            .order_id;
+
+        // the entry leaves the book without a trade behind the matcher's back
+        matcher
+            .books
+            .get_mut(&pair_id)
+            .unwrap()
+            .cancel_order(entry_id.clone())
+            .expect("Can't cancel entry");
+        matcher
+            .add_order(
+                pair_id.clone(),
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(1),
+            )
+            .expect("Can't add sell");
+
+        let bracket = matcher.get_bracket(entry_id).unwrap();
+        assert!(!bracket.entry_open);
+        assert!(bracket.children.is_empty());
+    }
+
+    #[test]
+    pub fn pass_bracket_activation_retried() {
+        let mut matcher = Matcher::new();
+
+        let pair_id = matcher
+            .add_pair(String::from("ETH"), String::from("INC"), dec!(100), 8)
+            .expect("Adding pair failed");
+        matcher
+            .set_price_band(
+                pair_id.clone(),
+                Some(PriceBand {
+                    width_bps: 500,
+                    on_breach: BreachAction::Halt,
+                    duration: 60,
+                }),
+            )
+            .expect("Can't set price band");
+        for price in [dec!(100), dec!(120)] {
+            matcher
+                .add_order(
+                    pair_id.clone(),
+                    Side::Sell,
+                    OrderKind::Limit,
+                    Some(price),
+                    dec!(1),
+                )
+                .expect("Can't add sell");
+        }
+
+        // the entry fills 1 and halts the pair before its children can enter
+        let report = matcher
+            .add_bracket_order(
+                pair_id.clone(),
+                OrderRequest {
+                    side: Side::Buy,
+                    kind: OrderKind::Market,
+                    price: None,
+                    quantity: dec!(2),
+                    options: OrderOptions::default(),
+                },
+                dec!(110),
+                dec!(90),
+            )
+            .expect("Can't add bracket");
+        assert_eq!(report.entry.filled_quantity, dec!(1));
+        assert!(report.children.is_empty());
+        assert_eq!(report.activation_error, Some(MatcherError::TradingHalted));
+
+        matcher.uncross(pair_id).expect("Can't uncross");
+        let bracket = matcher.get_bracket(report.entry.order_id).unwrap();
+        assert_eq!(bracket.children.len(), 1);
+        assert_eq!(bracket.activated_quantity, dec!(1));
+        assert_eq!(bracket.activation_error, None);
     }
 }
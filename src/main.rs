@@ -1,8 +1,8 @@
-mod exchange;
-mod orderbook;
-
-use exchange::Matcher;
-use orderbook::{order::OrderType, orderbook::OrderBook};
+use matcher::orderbook::{
+    order::{OrderKind, Side},
+    orderbook::OrderBook,
+};
+use rust_decimal_macros::dec;
 
 fn main() {
     // let mut matcher = Matcher::new();
@@ -10,32 +10,32 @@ fn main() {
 
     // let pair2 = matcher.add_pair(String::from("ETH"), String::from("USDT"), 12.0);
     // let order1 = matcher
-    //     .add_order(pair.clone(), OrderType::LimitSell, Some(12.1), 12.3)
+    //     .add_order(pair.clone(), Side::Sell, OrderKind::Limit, Some(12.1), 12.3)
     //     .unwrap();
 
     // let order2 = matcher
-    //     .add_order(pair2.clone(), OrderType::LimitBuy, Some(12.0), 12.1)
+    //     .add_order(pair2.clone(), Side::Buy, OrderKind::Limit, Some(12.0), 12.1)
     //     .unwrap();
 
     // dbg!("Before Deletion", &matcher, &order2);
 
     // matcher
-    //     .update_order(order1, OrderType::Buy, None, 11.9)
+    //     .update_order(order1, Some(OrderKind::Market), None, Some(11.9))
     //     .unwrap();
 
     // matcher.cancel_order(order2).unwrap();
-    let listing_price = 1023.0;
+    let listing_price = dec!(1023);
     let pair_id = String::from("ETHINC");
-    let quantity = 23243.5;
+    let quantity = dec!(23243.5);
 
-    let mut book = OrderBook::new(pair_id, listing_price);
+    let mut book = OrderBook::new(pair_id, listing_price, 8);
 
-    let buy = book
-        .add_order(OrderType::LimitBuy, Some(listing_price), quantity)
+    let _buy = book
+        .add_order(Side::Buy, OrderKind::Limit, Some(listing_price), quantity)
         .expect("can't add limit buy with price");
 
-    let sell = book
-        .add_order(OrderType::LimitSell, Some(listing_price), quantity)
+    let _sell = book
+        .add_order(Side::Sell, OrderKind::Limit, Some(listing_price), quantity)
         .expect("Can't add limit sell with price");
 
     // assert_eq!(book.buy_volume, 0.0);
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, VecDeque};
+
+use rust_decimal::Decimal;
+
+use super::{order::Order, rules::InstrumentRules};
+
+// how a price level shares an incoming order's quantity among its resting orders
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub enum AllocationPolicy {
+    // strict time priority
+    #[default]
+    Fifo,
+    // in proportion to displayed size, shares under the minimum allocation get nothing
+    ProRata {
+        min_allocation: Decimal,
+    },
+    // the first order in the queue fills first, the rest of the level is shared pro-rata
+    TopOrderProRata {
+        min_allocation: Decimal,
+    },
+}
+
+// quantity each order of a level may trade against `quantity`, keyed by order id; None under
+// FIFO where the queue alone decides. Shares are rounded down to whole lots at the quantity
+// scale and whatever rounding and the minimum allocation leave over goes to the queue in time
+// priority.
+pub fn allocate(
+    policy: AllocationPolicy,
+    rules: &InstrumentRules,
+    quantity_scale: u32,
+    level: &VecDeque<Order>,
+    quantity: Decimal,
+) -> Option<HashMap<String, Decimal>> {
+    let (min_allocation, top_order_first) = match policy {
+        AllocationPolicy::Fifo => return None,
+        AllocationPolicy::ProRata { min_allocation } => (min_allocation, false),
+        AllocationPolicy::TopOrderProRata { min_allocation } => (min_allocation, true),
+    };
+
+    let mut allocations = HashMap::new();
+    let mut left = quantity;
+
+    let mut orders: Vec<&Order> = level.iter().collect();
+    if top_order_first && !orders.is_empty() {
+        let top = orders.remove(0);
+        let share = left.min(top.visible_quantity());
+        allocations.insert(top.id().clone(), share);
+        left -= share;
+    }
+
+    let total: Decimal = orders.iter().map(|order| order.visible_quantity()).sum();
+    if left >= total {
+        for order in orders {
+            allocations.insert(order.id().clone(), order.visible_quantity());
+        }
+        return Some(allocations);
+    }
+
+    let mut shares: Vec<Decimal> = orders
+        .iter()
+        .map(|order| {
+            let share = rules.round_to_lot(left * order.visible_quantity() / total, quantity_scale);
+            if share < min_allocation {
+                Decimal::ZERO
+            } else {
+                share
+            }
+        })
+        .collect();
+
+    let mut leftover = left - shares.iter().sum::<Decimal>();
+    for (order, share) in orders.iter().zip(shares.iter_mut()) {
+        let extra = leftover.min(order.visible_quantity() - *share);
+        *share += extra;
+        leftover -= extra;
+    }
+
+    for (order, share) in orders.into_iter().zip(shares) {
+        allocations.insert(order.id().clone(), share);
+    }
+    Some(allocations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::order::{OrderKind, OrderOptions, Side};
+    use rust_decimal_macros::dec;
+
+    fn new_level(quantities: &[Decimal]) -> VecDeque<Order> {
+        quantities
+            .iter()
+            .enumerate()
+            .map(|(i, quantity)| {
+                Order::new(
+                    format!("order-{i}"),
+                    Side::Sell,
+                    OrderKind::Limit,
+                    *quantity,
+                    Some(dec!(100)),
+                    OrderOptions::default(),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn pass_pro_rata_allocation() {
+        let level = new_level(&[dec!(1), dec!(3), dec!(6)]);
+        let rules = InstrumentRules::default();
+
+        assert_eq!(
+            allocate(AllocationPolicy::Fifo, &rules, 0, &level, dec!(5)),
+            None
+        );
+
+        // 0.5 rounds down to nothing and 1.5 to 1, the leftover goes to the front of the queue
+        let allocations = allocate(
+            AllocationPolicy::ProRata {
+                min_allocation: Decimal::ZERO,
+            },
+            &rules,
+            0,
+            &level,
+            dec!(5),
+        )
+        .unwrap();
+        assert_eq!(allocations["order-0"], dec!(1));
+        assert_eq!(allocations["order-1"], dec!(1));
+        assert_eq!(allocations["order-2"], dec!(3));
+
+        // the top order fills first, the share under the minimum allocation goes back to the
+        // queue
+        let level = new_level(&[dec!(1), dec!(6), dec!(3)]);
+        let allocations = allocate(
+            AllocationPolicy::TopOrderProRata {
+                min_allocation: dec!(3),
+            },
+            &rules,
+            0,
+            &level,
+            dec!(7),
+        )
+        .unwrap();
+        assert_eq!(allocations["order-0"], dec!(1));
+        assert_eq!(allocations["order-1"], dec!(6));
+        assert_eq!(allocations["order-2"], dec!(0));
+
+        // at a finer scale the shares still come in whole lots
+        let level = new_level(&[dec!(1), dec!(2)]);
+        let rules = InstrumentRules {
+            lot_size: Some(dec!(1)),
+            ..Default::default()
+        };
+        let allocations = allocate(
+            AllocationPolicy::ProRata {
+                min_allocation: Decimal::ZERO,
+            },
+            &rules,
+            8,
+            &level,
+            dec!(1),
+        )
+        .unwrap();
+        assert_eq!(allocations["order-0"], dec!(1));
+        assert_eq!(allocations["order-1"], dec!(0));
+    }
+}
@@ -0,0 +1,101 @@
+use rust_decimal::Decimal;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub enum TradingPhase {
+    // incoming orders match as they arrive
+    #[default]
+    Continuous,
+    // orders only accumulate until the book uncrosses at a single price
+    Auction,
+    // a circuit breaker tripped, the book takes no orders until it reopens with an uncross
+    Halted,
+}
+
+// where the book would uncross right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicativeUncross {
+    pub price: Decimal,
+    pub volume: Decimal,
+    // buy quantity left unfilled at the price minus sell quantity left unfilled
+    pub imbalance: Decimal,
+}
+
+// price that executes the most volume between the bid and ask levels, given as (price,
+// quantity) pairs; ties go to the smallest imbalance, then to the price closest to the
+// reference, then to the lower price
+pub fn equilibrium(
+    bids: &[(Decimal, Decimal)],
+    asks: &[(Decimal, Decimal)],
+    reference_price: Decimal,
+) -> Option<IndicativeUncross> {
+    bids.iter()
+        .chain(asks.iter())
+        .map(|(price, _)| {
+            let demand: Decimal = bids
+                .iter()
+                .filter(|(bid, _)| bid >= price)
+                .map(|(_, quantity)| *quantity)
+                .sum();
+            let supply: Decimal = asks
+                .iter()
+                .filter(|(ask, _)| ask <= price)
+                .map(|(_, quantity)| *quantity)
+                .sum();
+            IndicativeUncross {
+                price: *price,
+                volume: demand.min(supply),
+                imbalance: demand - supply,
+            }
+        })
+        .filter(|uncross| !uncross.volume.is_zero())
+        .min_by_key(|uncross| {
+            (
+                -uncross.volume,
+                uncross.imbalance.abs(),
+                (uncross.price - reference_price).abs(),
+                uncross.price,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    pub fn pass_equilibrium() {
+        assert_eq!(
+            equilibrium(&[(dec!(99), dec!(1))], &[(dec!(100), dec!(1))], dec!(100)),
+            None
+        );
+
+        // 101 and 100 both execute 3, 100 leaves the smaller imbalance
+        let bids = [
+            (dec!(102), dec!(2)),
+            (dec!(101), dec!(1)),
+            (dec!(100), dec!(1)),
+        ];
+        let asks = [
+            (dec!(99), dec!(2)),
+            (dec!(100), dec!(1)),
+            (dec!(101), dec!(3)),
+        ];
+        let uncross = equilibrium(&bids, &asks, dec!(100)).unwrap();
+        assert_eq!(uncross.price, dec!(100));
+        assert_eq!(uncross.volume, dec!(3));
+        assert_eq!(uncross.imbalance, dec!(1));
+
+        // equal volume and imbalance on either price, the reference decides
+        let bids = [(dec!(101), dec!(1))];
+        let asks = [(dec!(99), dec!(1))];
+        assert_eq!(
+            equilibrium(&bids, &asks, dec!(100.8)).unwrap().price,
+            dec!(101)
+        );
+        assert_eq!(
+            equilibrium(&bids, &asks, dec!(99.5)).unwrap().price,
+            dec!(99)
+        );
+    }
+}
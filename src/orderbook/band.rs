@@ -0,0 +1,55 @@
+use rust_decimal::Decimal;
+
+use super::auction::TradingPhase;
+
+// what the book does when a trade would print outside the band
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BreachAction {
+    // no orders are accepted until the pair reopens through an uncross
+    Halt,
+    // orders keep accumulating in an auction that uncrosses when the period ends
+    VolatilityAuction,
+}
+
+// range around the reference price, the last traded price, within which trades may print
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct PriceBand {
+    pub width_bps: u32,
+    pub on_breach: BreachAction,
+    // seconds the pair stays halted or in auction after a breach
+    pub duration: i64,
+}
+
+impl PriceBand {
+    pub fn is_breached(&self, reference_price: Decimal, price: Decimal) -> bool {
+        (price - reference_price).abs() * Decimal::from(10_000)
+            > reference_price * Decimal::from(self.width_bps)
+    }
+
+    pub fn breach_phase(&self) -> TradingPhase {
+        match self.on_breach {
+            BreachAction::Halt => TradingPhase::Halted,
+            BreachAction::VolatilityAuction => TradingPhase::Auction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    pub fn pass_band_breach() {
+        let band = PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::Halt,
+            duration: 60,
+        };
+
+        assert!(!band.is_breached(dec!(100), dec!(105)));
+        assert!(!band.is_breached(dec!(100), dec!(95)));
+        assert!(band.is_breached(dec!(100), dec!(105.01)));
+        assert!(band.is_breached(dec!(100), dec!(94.99)));
+    }
+}
@@ -0,0 +1,15 @@
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthLevel {
+    pub price: Decimal,
+    // displayed quantity only, iceberg reserves are not shown
+    pub quantity: Decimal,
+}
+
+// market data snapshot of the best price levels on each side, best first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Depth {
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+
+use super::order::{Order, OrderStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEventKind {
+    Accepted,
+    // a stop reached its trigger price and became the order it carries
+    Triggered,
+    // quantity or price changed by an amend, a peg move or a one-cancels-other fill
+    Amended,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Expired,
+    Rejected,
+}
+
+// one step of an order's life, with the order as it stood right after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEvent {
+    pub kind: OrderEventKind,
+    pub status: OrderStatus,
+    pub price: Option<Decimal>,
+    // open quantity left after the event
+    pub quantity: Decimal,
+    // quantity traded by this event, zero for anything but fills
+    pub traded_quantity: Decimal,
+    pub timestamp: i64,
+}
+
+// append-only record of every order the book has seen, kept after orders leave the book
+#[derive(Debug, Default)]
+pub struct OrderHistory {
+    events: HashMap<String, Vec<OrderEvent>>,
+}
+
+impl OrderHistory {
+    pub fn record(&mut self, order: &Order, kind: OrderEventKind) {
+        self.push(order, kind, Decimal::ZERO);
+    }
+
+    pub fn record_fill(&mut self, order: &Order, traded_quantity: Decimal) {
+        let kind = if order.is_filled() {
+            OrderEventKind::Filled
+        } else {
+            OrderEventKind::PartiallyFilled
+        };
+        self.push(order, kind, traded_quantity);
+    }
+
+    pub fn get(&self, order_id: &String) -> Option<&[OrderEvent]> {
+        self.events.get(order_id).map(Vec::as_slice)
+    }
+
+    fn push(&mut self, order: &Order, kind: OrderEventKind, traded_quantity: Decimal) {
+        self.events
+            .entry(order.id().clone())
+            .or_default()
+            .push(OrderEvent {
+                kind,
+                status: *order.status(),
+                price: *order.price(),
+                quantity: order.quantity(),
+                traded_quantity,
+                timestamp: Utc::now().timestamp(),
+            });
+    }
+}
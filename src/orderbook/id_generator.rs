@@ -5,6 +5,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub struct IdGenerator {
     pair_id: String,
     counter: AtomicU64,
+    trade_counter: AtomicU64,
+    group_counter: AtomicU64,
 }
 
 impl IdGenerator {
@@ -12,6 +14,8 @@ impl IdGenerator {
         IdGenerator {
             pair_id,
             counter: AtomicU64::new(0),
+            trade_counter: AtomicU64::new(0),
+            group_counter: AtomicU64::new(0),
         }
     }
 
@@ -28,6 +32,39 @@ impl IdGenerator {
         // Format ID as "POOLID-TIMESTAMP-COUNTER"
         format!("{}-{:x}-{:06x}", self.pair_id, timestamp, count)
     }
+
+    pub fn generate_trade_id(&self) -> String {
+        let count = self.trade_counter.fetch_add(1, Ordering::SeqCst);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis();
+
+        // Format ID as "POOLID-T-TIMESTAMP-COUNTER"
+        format!("{}-T-{:x}-{:06x}", self.pair_id, timestamp, count)
+    }
+
+    pub fn generate_group_id(&self) -> String {
+        let count = self.group_counter.fetch_add(1, Ordering::SeqCst);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis();
+
+        // Format ID as "POOLID-G-TIMESTAMP-COUNTER"
+        format!("{}-G-{:x}-{:06x}", self.pair_id, timestamp, count)
+    }
+}
+
+// position of an order in its pair's arrival sequence, the counter its id ends with
+pub fn sequence(order_id: &str) -> u64 {
+    order_id
+        .rsplit('-')
+        .next()
+        .and_then(|count| u64::from_str_radix(count, 16).ok())
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -50,5 +87,6 @@ mod tests {
 
         assert_eq!(*vec[0].to_string(), pair_id);
         assert_eq!(vec[2], "000001");
+        assert_eq!(sequence(&binding), 1);
     }
 }
@@ -1,4 +1,13 @@
+pub mod allocation;
+pub mod auction;
+pub mod band;
+pub mod depth;
+pub mod history;
 mod id_generator;
+#[allow(clippy::module_inception)]
 pub mod orderbook;
 
 pub mod order;
+pub mod report;
+pub mod rules;
+pub mod ticker;
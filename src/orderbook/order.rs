@@ -1,12 +1,191 @@
 use chrono::Utc;
-use rust_decimal::{prelude::FromPrimitive, Decimal};
+use rust_decimal::Decimal;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum OrderType {
+use crate::error::MatcherError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Side {
     Buy,
     Sell,
-    LimitSell,
-    LimitBuy,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OrderKind {
+    Market,
+    Limit,
+    // becomes a market order once the last traded price reaches the stop price
+    Stop {
+        stop_price: Decimal,
+    },
+    // becomes a limit order once the last traded price reaches the stop price
+    StopLimit {
+        stop_price: Decimal,
+    },
+    // stop whose trigger trails the last traded price by the offset, becomes a market order
+    TrailingStop {
+        offset: TrailingOffset,
+    },
+    // trailing stop that becomes a limit order priced limit_offset beyond the trigger
+    TrailingStopLimit {
+        offset: TrailingOffset,
+        limit_offset: Decimal,
+    },
+    // rests like a limit order whose price the book keeps at the reference, offset away from
+    // the market and never beyond the cap
+    Pegged {
+        reference: PegReference,
+        offset: Decimal,
+        cap: Option<Decimal>,
+    },
+}
+
+impl OrderKind {
+    pub fn stop_price(&self) -> Option<Decimal> {
+        match self {
+            OrderKind::Stop { stop_price } | OrderKind::StopLimit { stop_price } => {
+                Some(*stop_price)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn trailing_offset(&self) -> Option<TrailingOffset> {
+        match self {
+            OrderKind::TrailingStop { offset } | OrderKind::TrailingStopLimit { offset, .. } => {
+                Some(*offset)
+            }
+            _ => None,
+        }
+    }
+
+    // orders that wait in the trigger book rather than the price levels
+    pub fn is_stop(&self) -> bool {
+        self.stop_price().is_some() || self.trailing_offset().is_some()
+    }
+
+    pub fn is_pegged(&self) -> bool {
+        matches!(self, OrderKind::Pegged { .. })
+    }
+}
+
+// top of book price a pegged order follows
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PegReference {
+    // best price on the order's own side
+    Primary,
+    // best price on the opposite side
+    Market,
+    // halfway between the best bid and the best ask
+    Midpoint,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TrailingOffset {
+    Amount(Decimal),
+    BasisPoints(u32),
+}
+
+impl TrailingOffset {
+    pub fn amount(&self, reference_price: Decimal) -> Decimal {
+        match self {
+            TrailingOffset::Amount(amount) => *amount,
+            TrailingOffset::BasisPoints(bps) => {
+                reference_price * Decimal::from(*bps) / Decimal::from(10_000)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TimeInForce {
+    // good till cancelled
+    #[default]
+    Gtc,
+    // immediate or cancel, whatever does not fill at once is cancelled
+    Ioc,
+    // fill or kill, fills completely at once or not at all
+    Fok,
+    // good till date, expires at the given unix timestamp in seconds
+    Gtd(i64),
+    // expires when the trading session ends
+    Day,
+}
+
+// what a post-only limit order does when it would take liquidity
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PostOnly {
+    Reject,
+    // rests one tick behind the best opposite price instead
+    Reprice,
+}
+
+// what the book does when an order would trade against another order of the same owner
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum SelfTradePrevention {
+    // cancels the remainder of the incoming order
+    #[default]
+    CancelNewest,
+    // cancels the resting order and keeps matching
+    CancelOldest,
+    CancelBoth,
+    // takes the smaller quantity off both, cancelling whichever has nothing left
+    DecrementAndCancel,
+}
+
+// entry parameters beyond side, kind, price and quantity
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrderOptions {
+    pub time_in_force: TimeInForce,
+    pub post_only: Option<PostOnly>,
+    // iceberg peak, only this much of the order is displayed at a time
+    pub display_quantity: Option<Decimal>,
+    // matches like any limit order but never shows in market data
+    pub hidden: bool,
+    // account the order belongs to, orders of the same owner never trade with each other
+    pub owner: Option<String>,
+    // applied when this order is the incoming one
+    pub self_trade_prevention: SelfTradePrevention,
+}
+
+// a complete order submission, for calls that enter several orders at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub side: Side,
+    pub kind: OrderKind,
+    pub price: Option<Decimal>,
+    pub quantity: Decimal,
+    pub options: OrderOptions,
+}
+
+// selects open orders for a mass cancel, every unset field matches anything; parked stops are
+// matched on their stop price
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CancelFilter {
+    pub side: Option<Side>,
+    pub owner: Option<String>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+}
+
+impl CancelFilter {
+    pub fn matches(&self, order: &Order, price: Decimal) -> bool {
+        self.side.is_none_or(|side| side == *order.side())
+            && self
+                .owner
+                .as_ref()
+                .is_none_or(|owner| order.owner().as_ref() == Some(owner))
+            && self.min_price.is_none_or(|min| price >= min)
+            && self.max_price.is_none_or(|max| price <= max)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,14 +194,29 @@ pub enum OrderStatus {
     Executed,
     Cancelled,
     PartiallyExecuted,
+    Expired,
+    Rejected,
 }
 
 #[derive(Debug, Clone)]
 pub struct Order {
     id: String,
-    quantity: f64,
-    order_type: OrderType,
+    side: Side,
+    kind: OrderKind,
+    quantity: Decimal,
     price: Option<Decimal>,
+    // current trigger level of a trailing stop, ratcheted by the book
+    trail_price: Option<Decimal>,
+    // quote amount a quote-denominated market order has left to spend
+    quote_budget: Option<Decimal>,
+    time_in_force: TimeInForce,
+    post_only: Option<PostOnly>,
+    display_quantity: Option<Decimal>,
+    // displayed slice of an iceberg, the whole remaining quantity otherwise
+    visible_quantity: Decimal,
+    hidden: bool,
+    owner: Option<String>,
+    self_trade_prevention: SelfTradePrevention,
     status: OrderStatus,
     timestamp: i64,
 }
@@ -30,29 +224,94 @@ pub struct Order {
 impl Order {
     pub fn new(
         id: String,
-        quantity: f64,
-        order_type: OrderType,
-        price: Option<f64>,
-    ) -> Result<Order, String> {
-        let price = match price {
-            Some(p) => Some(Decimal::from_f64(p).unwrap()),
-            None => {
-                if matches!(order_type, OrderType::LimitBuy | OrderType::LimitSell)
-                    && price.is_none()
-                {
-                    return Err("Limit Order needs a price".to_owned());
-                };
-
-                None
+        side: Side,
+        kind: OrderKind,
+        quantity: Decimal,
+        price: Option<Decimal>,
+        options: OrderOptions,
+    ) -> Result<Order, MatcherError> {
+        if quantity <= Decimal::ZERO {
+            return Err(MatcherError::InvalidQuantity);
+        }
+
+        let price = match kind {
+            OrderKind::Limit | OrderKind::StopLimit { .. } => {
+                Some(price.ok_or(MatcherError::MissingLimitPrice)?)
+            }
+            _ => None,
+        };
+
+        if let Some(stop_price) = kind.stop_price() {
+            if stop_price <= Decimal::ZERO {
+                return Err(MatcherError::InvalidStopPrice(stop_price));
             }
+        }
+
+        let invalid_offset = match kind {
+            OrderKind::TrailingStop { offset } => offset.amount(Decimal::ONE) <= Decimal::ZERO,
+            OrderKind::TrailingStopLimit {
+                offset,
+                limit_offset,
+            } => offset.amount(Decimal::ONE) <= Decimal::ZERO || limit_offset < Decimal::ZERO,
+            _ => false,
         };
+        if invalid_offset {
+            return Err(MatcherError::InvalidTrailingOffset);
+        }
+
+        if let OrderKind::Pegged { offset, cap, .. } = kind {
+            if offset < Decimal::ZERO || cap.is_some_and(|cap| cap <= Decimal::ZERO) {
+                return Err(MatcherError::InvalidPeg);
+            }
+        }
+
+        if options.post_only.is_some() && kind != OrderKind::Limit {
+            return Err(MatcherError::PostOnlyRequiresLimit);
+        }
+
+        let rests_as_limit = matches!(
+            kind,
+            OrderKind::Limit
+                | OrderKind::StopLimit { .. }
+                | OrderKind::TrailingStopLimit { .. }
+                | OrderKind::Pegged { .. }
+        );
+
+        if let Some(display_quantity) = options.display_quantity {
+            if display_quantity <= Decimal::ZERO || !rests_as_limit || options.hidden {
+                return Err(MatcherError::InvalidDisplayQuantity);
+            }
+        }
+
+        if options.hidden && !rests_as_limit {
+            return Err(MatcherError::HiddenRequiresLimit);
+        }
+
         let dt = Utc::now();
 
+        if let TimeInForce::Gtd(expiry) = options.time_in_force {
+            if expiry <= dt.timestamp() {
+                return Err(MatcherError::InvalidExpiry(expiry));
+            }
+        }
+
         Ok(Order {
             id,
+            side,
+            kind,
             quantity,
-            order_type,
             price,
+            trail_price: None,
+            quote_budget: None,
+            time_in_force: options.time_in_force,
+            post_only: options.post_only,
+            display_quantity: options.display_quantity,
+            visible_quantity: options
+                .display_quantity
+                .map_or(quantity, |display_quantity| display_quantity.min(quantity)),
+            hidden: options.hidden,
+            owner: options.owner,
+            self_trade_prevention: options.self_trade_prevention,
             timestamp: dt.timestamp(),
             status: OrderStatus::Open,
         })
@@ -60,31 +319,42 @@ impl Order {
 
     pub fn update(
         &mut self,
-        order_type: Option<OrderType>,
-        price: Option<f64>,
-        quantity: Option<f64>,
-    ) -> Result<Self, String> {
+        kind: Option<OrderKind>,
+        price: Option<Decimal>,
+        quantity: Option<Decimal>,
+    ) -> Result<Self, MatcherError> {
         if matches!(self.status, OrderStatus::Cancelled) {
-            return Err("Order already cancelled".to_owned());
+            return Err(MatcherError::OrderAlreadyCancelled(self.id.clone()));
         }
 
-        if order_type.is_some() {
-            //  when price of a limit order is updated
-            let order_type = order_type.unwrap();
-            if matches!(order_type, OrderType::LimitBuy | OrderType::LimitSell) && price.is_none() {
-                return Err("Limit Order needs a price".to_owned());
-            }
-
-            self.price = if matches!(order_type, OrderType::Buy | OrderType::Sell) {
-                None
-            } else {
-                Decimal::from_f64(price.unwrap())
+        if let Some(kind) = kind {
+            // a limit order keeps its current price unless given a new one
+            self.price = match kind {
+                OrderKind::Limit => Some(
+                    price
+                        .or(self.price)
+                        .ok_or(MatcherError::MissingLimitPrice)?,
+                ),
+                OrderKind::Market => None,
+                _ => return Err(MatcherError::OrderNotAmendable(self.id.clone())),
             };
-            self.order_type = order_type;
+            self.kind = kind;
+        } else if let Some(price) = price {
+            // only a limit order has a price of its own to move
+            if self.kind != OrderKind::Limit {
+                return Err(MatcherError::OrderNotAmendable(self.id.clone()));
+            }
+            self.price = Some(price);
         }
 
-        if quantity.is_some() {
-            self.quantity = quantity.unwrap();
+        if let Some(quantity) = quantity {
+            if quantity <= Decimal::ZERO {
+                return Err(MatcherError::InvalidQuantity);
+            }
+            self.quantity = quantity;
+            self.visible_quantity = self
+                .display_quantity
+                .map_or(quantity, |display_quantity| display_quantity.min(quantity));
         }
         let dt = Utc::now();
         self.timestamp = dt.timestamp();
@@ -92,29 +362,123 @@ impl Order {
         Ok(self.clone())
     }
 
-    pub fn fill_order(&mut self, amount: f64) {
+    pub fn fill_order(&mut self, amount: Decimal) {
         self.quantity -= amount;
-        if self.quantity == 0.0 {
+        self.visible_quantity = match self.display_quantity {
+            Some(_) => self.visible_quantity - amount.min(self.visible_quantity),
+            None => self.quantity,
+        };
+        if self.quantity.is_zero() {
             self.status = OrderStatus::Executed
         } else {
             self.status = OrderStatus::PartiallyExecuted
         }
     }
 
-    pub fn cancel(&mut self) -> Result<(), String> {
-        if self.status != OrderStatus::Open {
-            return Err("Order can't be cancalled".to_string());
-        }
+    // takes quantity off an order without a trade, the displayed slice never exceeds what is left
+    pub fn reduce(&mut self, amount: Decimal) {
+        self.quantity -= amount;
+        self.visible_quantity = self.visible_quantity.min(self.quantity);
+    }
 
+    // marks whatever is left of the order cancelled, whether the user or the book cancels it
+    pub fn cancel_remainder(&mut self) {
         self.status = OrderStatus::Cancelled;
-        Ok(())
+    }
+
+    // turns a triggered stop into the market or limit order it carries
+    pub fn trigger(&mut self) {
+        self.kind = match self.kind {
+            OrderKind::Stop { .. } | OrderKind::TrailingStop { .. } => OrderKind::Market,
+            OrderKind::StopLimit { .. } => OrderKind::Limit,
+            OrderKind::TrailingStopLimit { limit_offset, .. } => {
+                let trail_price = self.trail_price.unwrap();
+                self.price = Some(match self.side {
+                    Side::Buy => trail_price + limit_offset,
+                    Side::Sell => trail_price - limit_offset,
+                });
+                OrderKind::Limit
+            }
+            kind => kind,
+        };
+        // the trailing level means nothing once the stop has converted
+        self.trail_price = None;
+    }
+
+    // level at which a waiting stop converts, the stop price or the current trailing level
+    pub fn trigger_price(&self) -> Option<Decimal> {
+        self.kind.stop_price().or(self.trail_price)
+    }
+
+    pub fn set_trail_price(&mut self, trail_price: Decimal) {
+        self.trail_price = Some(trail_price);
+    }
+
+    pub fn set_quote_budget(&mut self, quote_budget: Decimal) {
+        self.quote_budget = Some(quote_budget);
+    }
+
+    pub fn spend_quote(&mut self, notional: Decimal) {
+        if let Some(quote_budget) = self.quote_budget.as_mut() {
+            *quote_budget -= notional;
+        }
+    }
+
+    pub fn quote_budget(&self) -> Option<Decimal> {
+        self.quote_budget
+    }
+
+    pub fn reject(&mut self) {
+        self.status = OrderStatus::Rejected;
+    }
+
+    pub fn reprice(&mut self, price: Decimal) {
+        self.price = Some(price);
+    }
+
+    pub fn expire(&mut self) {
+        self.status = OrderStatus::Expired;
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        matches!(self.time_in_force, TimeInForce::Gtd(expiry) if expiry <= now)
+    }
+
+    // an iceberg whose displayed slice is used up while reserve remains
+    pub fn needs_refresh(&self) -> bool {
+        self.visible_quantity.is_zero() && !self.quantity.is_zero()
+    }
+
+    // shows the next slice of an iceberg, the book requeues it behind the level
+    pub fn refresh(&mut self) {
+        if let Some(display_quantity) = self.display_quantity {
+            self.visible_quantity = display_quantity.min(self.quantity);
+            self.timestamp = Utc::now().timestamp();
+        }
+    }
+
+    pub fn visible_quantity(&self) -> Decimal {
+        self.visible_quantity
+    }
+
+    // what market data may show of this order
+    pub fn displayed_quantity(&self) -> Decimal {
+        if self.hidden {
+            Decimal::ZERO
+        } else {
+            self.visible_quantity
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
     }
 
     pub fn is_filled(&self) -> bool {
-        self.quantity == 0.0
+        self.quantity.is_zero()
     }
 
-    pub fn quantity(&self) -> f64 {
+    pub fn quantity(&self) -> Decimal {
         self.quantity
     }
 
@@ -122,38 +486,86 @@ impl Order {
         &self.id
     }
 
-    pub fn order_type(&self) -> &OrderType {
-        &self.order_type
+    pub fn side(&self) -> &Side {
+        &self.side
+    }
+
+    pub fn kind(&self) -> &OrderKind {
+        &self.kind
     }
 
     pub fn price(&self) -> &Option<Decimal> {
         &self.price
     }
+
+    pub fn status(&self) -> &OrderStatus {
+        &self.status
+    }
+
+    pub fn time_in_force(&self) -> &TimeInForce {
+        &self.time_in_force
+    }
+
+    pub fn post_only(&self) -> &Option<PostOnly> {
+        &self.post_only
+    }
+
+    pub fn owner(&self) -> &Option<String> {
+        &self.owner
+    }
+
+    pub fn self_trade_prevention(&self) -> &SelfTradePrevention {
+        &self.self_trade_prevention
+    }
+
+    // both orders belong to the same owner, so they must not trade
+    pub fn is_self_trade(&self, other: &Order) -> bool {
+        self.owner.is_some() && self.owner == other.owner
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     pub fn pass_creating_orders() {
-        let _buy_order =
-            Order::new("buy_order".to_owned(), 12.0, super::OrderType::Buy, None).unwrap();
-        let _sell_order =
-            Order::new("sell_order".to_owned(), 12.0, super::OrderType::Sell, None).unwrap();
+        let _buy_order = Order::new(
+            "buy_order".to_owned(),
+            super::Side::Buy,
+            super::OrderKind::Market,
+            dec!(12),
+            None,
+            OrderOptions::default(),
+        )
+        .unwrap();
+        let _sell_order = Order::new(
+            "sell_order".to_owned(),
+            super::Side::Sell,
+            super::OrderKind::Market,
+            dec!(12),
+            None,
+            OrderOptions::default(),
+        )
+        .unwrap();
 
         let _limit_buy_order = Order::new(
             "limit_buy_order".to_owned(),
-            12.0,
-            super::OrderType::LimitBuy,
-            Some(12.3),
+            super::Side::Buy,
+            super::OrderKind::Limit,
+            dec!(12),
+            Some(dec!(12.3)),
+            OrderOptions::default(),
         )
         .unwrap();
         let _sell_order = Order::new(
             "buy_order".to_owned(),
-            12.0,
-            super::OrderType::LimitBuy,
-            Some(12.54),
+            super::Side::Buy,
+            super::OrderKind::Limit,
+            dec!(12),
+            Some(dec!(12.54)),
+            OrderOptions::default(),
         )
         .unwrap();
     }
@@ -163,17 +575,40 @@ mod tests {
     pub fn fail_creating_orders() {
         let _limit_buy_order = Order::new(
             "limit_buy_order".to_owned(),
-            12.0,
-            super::OrderType::LimitBuy,
+            super::Side::Buy,
+            super::OrderKind::Limit,
+            dec!(12),
             None,
+            OrderOptions::default(),
         )
         .unwrap();
         let _sell_order = Order::new(
             "buy_order".to_owned(),
-            12.0,
-            super::OrderType::LimitBuy,
+            super::Side::Buy,
+            super::OrderKind::Limit,
+            dec!(12),
             None,
+            OrderOptions::default(),
         )
         .unwrap();
     }
+
+    #[test]
+    pub fn fail_creating_gtd_order_in_the_past() {
+        let options = OrderOptions {
+            time_in_force: TimeInForce::Gtd(Utc::now().timestamp() - 1),
+            ..Default::default()
+        };
+
+        let order = Order::new(
+            "gtd_order".to_owned(),
+            super::Side::Buy,
+            super::OrderKind::Limit,
+            dec!(12),
+            Some(dec!(12.3)),
+            options,
+        );
+
+        assert!(matches!(order, Err(MatcherError::InvalidExpiry(_))));
+    }
 }
@@ -1,372 +1,3453 @@
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 
 use super::{
-    id_generator::IdGenerator,
-    order::{Order, OrderType},
+    allocation::{allocate, AllocationPolicy},
+    auction::{equilibrium, IndicativeUncross, TradingPhase},
+    band::PriceBand,
+    depth::{Depth, DepthLevel},
+    history::{OrderEvent, OrderEventKind, OrderHistory},
+    id_generator::{sequence, IdGenerator},
+    order::{
+        CancelFilter, Order, OrderKind, OrderOptions, OrderRequest, OrderStatus, PegReference,
+        PostOnly, SelfTradePrevention, Side, TimeInForce, TrailingOffset,
+    },
+    report::{ExecutionReport, Fill, OcoReport, PegAmend, RejectReason, UncrossReport},
+    rules::InstrumentRules,
+    ticker::{Ticker, TradeWindow},
 };
 
-use rust_decimal::{prelude::FromPrimitive, Decimal};
+use crate::error::MatcherError;
 
-#[derive(Debug)]
-pub struct Trade {
-    order: Order,
-    book_order: Order,
-    quantity: f64,
-}
+use chrono::Utc;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+// orders queued per price, each level in time priority
+type PriceLevels = BTreeMap<Decimal, VecDeque<Order>>;
+
+const DEFAULT_REPRICE_STEP: Decimal = dec!(0.01);
 
 #[derive(Debug)]
 pub struct OrderBook {
     id_generator: IdGenerator,
-    buy_orders: BTreeMap<Decimal, VecDeque<Order>>,
-    sell_orders: BTreeMap<Decimal, VecDeque<Order>>,
-    pub sell_volume: f64,
-    pub buy_volume: f64,
+    // number of decimal places a quantity may carry on this pair
+    quantity_scale: u32,
+    // tick, lot, quantity and notional rules orders are checked against
+    rules: InstrumentRules,
+    // how an incoming order's quantity is shared among the orders of a price level
+    allocation_policy: AllocationPolicy,
+    phase: TradingPhase,
+    // circuit breaker around the last traded price, and when a tripped one reopens the book
+    price_band: Option<PriceBand>,
+    resume_at: Option<i64>,
+    buy_orders: PriceLevels,
+    sell_orders: PriceLevels,
+    // untriggered stop orders keyed by stop price, they hold no displayed liquidity
+    buy_stops: PriceLevels,
+    sell_stops: PriceLevels,
+    // total resting quantity per side, iceberg reserves included
+    pub sell_volume: Decimal,
+    pub buy_volume: Decimal,
     order_index: HashMap<String, Order>,
+    // resting GTD orders keyed by expiry, entries for orders that already left the book are skipped
+    expiries: BTreeSet<(i64, String)>,
     last_traded_price: Decimal,
+    last_traded_size: Decimal,
+    trade_window: TradeWindow,
+    // pegged order moves not yet collected through drain_peg_amends
+    peg_amends: Vec<PegAmend>,
+    // one-cancels-other legs keyed to their group and sibling, and the legs of each group
+    oco_legs: HashMap<String, (String, String)>,
+    oco_groups: HashMap<String, (String, String)>,
+    // trades on linked legs waiting to come off their siblings
+    oco_fills: Vec<(String, Decimal)>,
+    history: OrderHistory,
 }
 
 impl OrderBook {
-    pub fn new(pair_id: String, listing_price: f64) -> OrderBook {
+    pub fn new(pair_id: String, listing_price: Decimal, quantity_scale: u32) -> OrderBook {
         OrderBook {
             id_generator: IdGenerator::new(pair_id),
+            quantity_scale,
+            rules: InstrumentRules::default(),
+            allocation_policy: AllocationPolicy::default(),
+            phase: TradingPhase::default(),
+            price_band: None,
+            resume_at: None,
             buy_orders: BTreeMap::new(),
             sell_orders: BTreeMap::new(),
+            buy_stops: BTreeMap::new(),
+            sell_stops: BTreeMap::new(),
             order_index: HashMap::new(),
-            last_traded_price: Decimal::from_f64(listing_price).unwrap(),
-            sell_volume: 0.0,
-            buy_volume: 0.0,
+            expiries: BTreeSet::new(),
+            last_traded_price: listing_price,
+            last_traded_size: Decimal::ZERO,
+            trade_window: TradeWindow::new(listing_price),
+            peg_amends: Vec::new(),
+            oco_legs: HashMap::new(),
+            oco_groups: HashMap::new(),
+            oco_fills: Vec::new(),
+            history: OrderHistory::default(),
+            sell_volume: Decimal::ZERO,
+            buy_volume: Decimal::ZERO,
         }
     }
 
     pub fn add_order(
         &mut self,
-        order_type: OrderType,
-        price: Option<f64>,
-        quantity: f64,
-    ) -> Result<String, String> {
-        let id = self.id_generator.generate_order_id();
-        let order = Order::new(id.clone(), quantity, order_type, price)?;
+        side: Side,
+        kind: OrderKind,
+        price: Option<Decimal>,
+        quantity: Decimal,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.add_order_with_options(side, kind, price, quantity, OrderOptions::default())
+    }
 
-        self.order_index.insert(order.id().clone(), order.clone());
+    pub fn add_order_with_options(
+        &mut self,
+        side: Side,
+        kind: OrderKind,
+        price: Option<Decimal>,
+        quantity: Decimal,
+        options: OrderOptions,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.resume_trading(Utc::now().timestamp());
+        let mut order = self.new_order(side, kind, price, quantity, options)?;
+
+        self.expire_orders(Utc::now().timestamp());
+        self.peg_entry(&mut order)?;
+        self.history.record(&order, OrderEventKind::Accepted);
+
+        let mut report = self.process_order(order);
+        report.triggered = self.settle();
+        Ok(report)
+    }
 
-        if matches!(*order.order_type(), OrderType::Buy | OrderType::Sell) {
-            self.match_market_order(order);
-        } else {
-            self.match_limit_order(order);
+    // market order that spends, or for a sell raises, up to `quote_budget` in quote currency;
+    // it is sized to what the budget buys across the opposite side in whole lots and whatever
+    // it did not use comes back in the report
+    pub fn add_quote_market_order(
+        &mut self,
+        side: Side,
+        quote_budget: Decimal,
+        options: OrderOptions,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.resume_trading(Utc::now().timestamp());
+        self.expire_orders(Utc::now().timestamp());
+
+        let quantity = self.affordable_quantity(side, quote_budget);
+        if quantity.is_zero() {
+            return Err(MatcherError::QuoteBudgetUnfillable(quote_budget));
         }
-        return Ok(id);
-    }
 
-    fn match_market_order(&mut self, mut order: Order) {
-        let mut trades: Vec<Trade> = Vec::new();
+        let mut order = self.new_order(side, OrderKind::Market, None, quantity, options)?;
+        order.set_quote_budget(quote_budget);
+        self.history.record(&order, OrderEventKind::Accepted);
 
-        let (orders, volume) = if *order.order_type() == OrderType::Buy {
-            (&mut self.sell_orders, &mut self.sell_volume)
-        } else {
-            (&mut self.buy_orders, &mut self.buy_volume)
+        let mut report = self.process_order(order);
+        report.triggered = self.settle();
+        Ok(report)
+    }
+
+    // base quantity the budget reaches walking the opposite side best price first
+    fn affordable_quantity(&self, side: Side, quote_budget: Decimal) -> Decimal {
+        let levels: Box<dyn Iterator<Item = (&Decimal, &VecDeque<Order>)>> = match side {
+            Side::Buy => Box::new(self.sell_orders.iter()),
+            Side::Sell => Box::new(self.buy_orders.iter().rev()),
         };
 
-        for (_, orders) in orders.iter_mut() {
-            while let Some(book_order) = orders.front_mut() {
-                let traded_quantity = order.quantity().min(book_order.quantity());
+        let mut budget = quote_budget;
+        let mut quantity = Decimal::ZERO;
+        for (price, orders) in levels {
+            let available: Decimal = orders.iter().map(|order| order.quantity()).sum();
+            let level_quantity = self
+                .rules
+                .round_to_lot(budget / *price, self.quantity_scale)
+                .min(available);
+            if level_quantity.is_zero() {
+                break;
+            }
+            quantity += level_quantity;
+            budget -= level_quantity * *price;
+        }
+        quantity
+    }
 
-                book_order.fill_order(traded_quantity);
-                order.fill_order(traded_quantity);
+    // enters two orders where every trade on one comes off the other, and a leg that fills
+    // completely cancels its sibling; neither leg enters unless both are valid
+    pub fn add_oco_orders(
+        &mut self,
+        first: OrderRequest,
+        second: OrderRequest,
+    ) -> Result<OcoReport, MatcherError> {
+        self.resume_trading(Utc::now().timestamp());
+        let mut first_order = self.new_order(
+            first.side,
+            first.kind,
+            first.price,
+            first.quantity,
+            first.options,
+        )?;
+        let mut second_order = self.new_order(
+            second.side,
+            second.kind,
+            second.price,
+            second.quantity,
+            second.options,
+        )?;
+
+        self.expire_orders(Utc::now().timestamp());
+        self.peg_entry(&mut first_order)?;
+        self.peg_entry(&mut second_order)?;
+
+        // legs on opposite sides may not meet, a leg without a price could meet anything
+        let prices = match (first_order.side(), second_order.side()) {
+            (Side::Buy, Side::Sell) => Some((*first_order.price(), *second_order.price())),
+            (Side::Sell, Side::Buy) => Some((*second_order.price(), *first_order.price())),
+            _ => None,
+        };
+        let crosses = match prices {
+            Some((Some(bid), Some(ask))) => bid >= ask,
+            Some(_) => true,
+            None => false,
+        };
+        if crosses {
+            return Err(MatcherError::OcoLegsCross);
+        }
+        self.history.record(&first_order, OrderEventKind::Accepted);
+        self.history.record(&second_order, OrderEventKind::Accepted);
+
+        let group_id = self.id_generator.generate_group_id();
+        let first = self.process_order(first_order);
+
+        // whatever the first leg traded on entry comes off the second before it enters
+        if first.status == OrderStatus::Executed || first.filled_quantity >= second_order.quantity()
+        {
+            second_order.cancel_remainder();
+            self.history
+                .record(&second_order, OrderEventKind::Cancelled);
+            self.order_index
+                .insert(second_order.id().clone(), second_order.clone());
+            let mut second = ExecutionReport::new(&second_order, Vec::new());
+            second.triggered = self.settle();
+            return Ok(OcoReport {
+                group_id,
+                first,
+                second,
+            });
+        }
+        second_order.reduce(first.filled_quantity);
+
+        let second_id = second_order.id().clone();
+        self.oco_legs.insert(
+            first.order_id.clone(),
+            (group_id.clone(), second_id.clone()),
+        );
+        self.oco_legs.insert(
+            second_id.clone(),
+            (group_id.clone(), first.order_id.clone()),
+        );
+        self.oco_groups
+            .insert(group_id.clone(), (first.order_id.clone(), second_id));
+
+        let mut second = self.process_order(second_order);
+        second.triggered = self.settle();
+        Ok(OcoReport {
+            group_id,
+            first,
+            second,
+        })
+    }
 
-                *volume -= traded_quantity;
+    // cancels whatever is still live of both legs of a one-cancels-other group
+    pub fn cancel_oco_group(&mut self, group_id: String) -> Result<(), MatcherError> {
+        let (first, second) = self
+            .oco_groups
+            .get(&group_id)
+            .cloned()
+            .ok_or_else(|| MatcherError::UnknownGroup(group_id.clone()))?;
+
+        for order_id in [first, second] {
+            if let Some(mut order) = self.take_resting(&order_id) {
+                self.order_index.remove(&order_id);
+                order.cancel_remainder();
+                self.history.record(&order, OrderEventKind::Cancelled);
+            }
+        }
+        self.unlink_oco(&group_id);
 
-                trades.push(Trade {
-                    book_order: book_order.clone(),
-                    order: order.clone(),
-                    quantity: traded_quantity,
-                });
+        self.settle();
+        Ok(())
+    }
 
-                if book_order.is_filled() {
-                    self.order_index.remove(book_order.id());
-                    orders.remove(0);
-                }
+    fn new_order(
+        &self,
+        side: Side,
+        kind: OrderKind,
+        price: Option<Decimal>,
+        quantity: Decimal,
+        options: OrderOptions,
+    ) -> Result<Order, MatcherError> {
+        self.check_quantity_scale(quantity)?;
+
+        if let Some(display_quantity) = options.display_quantity {
+            self.check_quantity_scale(display_quantity)?;
+            self.rules.check_lot(display_quantity)?;
+        }
 
-                if order.is_filled() {
-                    break;
-                }
-            }
+        self.check_phase(&kind, &options.time_in_force)?;
+
+        let id = self.id_generator.generate_order_id();
+        let order = Order::new(id, side, kind, quantity, price, options)?;
+        self.check_rules(&order)?;
+        Ok(order)
+    }
 
-            println!("Trades happened: {:?} ", trades);
+    // nothing trades before the uncross, orders that can only take liquidity at once are
+    // turned away
+    fn check_phase(
+        &self,
+        kind: &OrderKind,
+        time_in_force: &TimeInForce,
+    ) -> Result<(), MatcherError> {
+        let immediate = *kind == OrderKind::Market
+            || matches!(time_in_force, TimeInForce::Ioc | TimeInForce::Fok);
+        match self.phase {
+            TradingPhase::Halted => Err(MatcherError::TradingHalted),
+            TradingPhase::Auction if immediate => Err(MatcherError::NotAllowedInAuction),
+            _ => Ok(()),
         }
     }
 
-    fn match_limit_order(&mut self, mut order: Order) {
-        let (orders, volume) = if *order.order_type() == OrderType::LimitBuy {
-            (
-                self.sell_orders.get_mut(&order.price().unwrap()),
-                &mut self.sell_volume,
-            )
-        } else {
-            (
-                self.buy_orders.get_mut(&order.price().unwrap()),
-                &mut self.buy_volume,
-            )
+    // every price the order carries must sit on the tick, orders without a price of their own
+    // are valued at the last traded price for the notional check
+    fn check_rules(&self, order: &Order) -> Result<(), MatcherError> {
+        let cap = match order.kind() {
+            OrderKind::Pegged { cap, .. } => *cap,
+            _ => None,
         };
+        for price in [*order.price(), order.kind().stop_price(), cap]
+            .into_iter()
+            .flatten()
+        {
+            self.rules.check_price(price)?;
+        }
 
-        println!("Book Orders {orders:#?}, order: {order:#?}");
+        self.rules.check_quantity(order.quantity())?;
 
-        match orders {
-            None => {
-                if *order.order_type() == OrderType::LimitBuy {
-                    self.buy_volume += order.quantity();
-                    self.buy_orders
-                        .entry(order.price().unwrap())
-                        .or_insert(VecDeque::new())
-                        .push_back(order);
-                } else {
-                    self.sell_volume += order.quantity();
-                    self.sell_orders
-                        .entry(order.price().unwrap())
-                        .or_insert(VecDeque::new())
-                        .push_back(order);
-                }
+        let notional_price = order
+            .price()
+            .or(order.kind().stop_price())
+            .unwrap_or(self.last_traded_price);
+        self.rules.check_notional(notional_price, order.quantity())
+    }
+
+    // prices a pegged order off the top of book it enters against
+    fn peg_entry(&self, order: &mut Order) -> Result<(), MatcherError> {
+        if order.kind().is_pegged() {
+            let peg_price = self.peg_price(order).ok_or(MatcherError::PegUnavailable)?;
+            order.reprice(peg_price);
+        }
+        Ok(())
+    }
+
+    // takes each trade on a linked leg off its sibling, cancelling the sibling once the leg is
+    // filled or nothing of the sibling is left
+    fn apply_oco_fills(&mut self) {
+        for (order_id, quantity) in std::mem::take(&mut self.oco_fills) {
+            let Some((group_id, sibling_id)) = self.oco_legs.get(&order_id).cloned() else {
+                continue;
+            };
+
+            let leg_filled = self
+                .order_index
+                .get(&order_id)
+                .is_none_or(|order| order.is_filled());
+            let remaining = self.reduce_resting(&sibling_id, quantity);
+
+            if leg_filled || remaining.is_none_or(|remaining| remaining.is_zero()) {
+                self.cancel_resting(&sibling_id);
+                self.unlink_oco(&group_id);
+            } else if let Some(sibling) = self.order_index.get(&sibling_id) {
+                self.history.record(sibling, OrderEventKind::Amended);
             }
-            Some(orders) => {
-                let mut trades: Vec<Trade> = Vec::new();
-                while let Some(book_order) = orders.front_mut() {
-                    let traded_quantity = order.quantity().min(book_order.quantity());
+        }
+    }
 
-                    book_order.fill_order(traded_quantity);
-                    order.fill_order(traded_quantity);
+    // cancels a resting order on the book's own account, it stays queryable as cancelled
+    fn cancel_resting(&mut self, order_id: &String) {
+        if let Some(mut order) = self.take_resting(order_id) {
+            order.cancel_remainder();
+            self.history.record(&order, OrderEventKind::Cancelled);
+            self.order_index.insert(order_id.clone(), order);
+        }
+    }
+
+    fn unlink_oco(&mut self, group_id: &String) {
+        if let Some((first, second)) = self.oco_groups.remove(group_id) {
+            self.oco_legs.remove(&first);
+            self.oco_legs.remove(&second);
+        }
+    }
 
-                    *volume -= traded_quantity;
+    fn process_order(&mut self, mut order: Order) -> ExecutionReport {
+        self.order_index.insert(order.id().clone(), order.clone());
 
-                    trades.push(Trade {
-                        book_order: book_order.clone(),
-                        order: order.clone(),
-                        quantity: traded_quantity,
-                    });
+        if order.kind().is_stop() {
+            if let Some(offset) = order.kind().trailing_offset() {
+                order.set_trail_price(self.trail_level(*order.side(), offset));
+            }
+            let trigger_price = order.trigger_price().unwrap();
+            if !self.is_triggered(*order.side(), trigger_price)
+                || self.phase != TradingPhase::Continuous
+            {
+                return self.park_stop_order(order, trigger_price);
+            }
+            self.trigger(&mut order);
+        }
 
-                    if book_order.is_filled() {
-                        self.order_index.remove(book_order.id());
-                        orders.remove(0);
-                    }
+        // fill or kill leaves the book untouched when it can't fill completely
+        if *order.time_in_force() == TimeInForce::Fok && !self.fills_completely(&order) {
+            order.cancel_remainder();
+            self.history.record(&order, OrderEventKind::Cancelled);
+            self.order_index.insert(order.id().clone(), order.clone());
+            return ExecutionReport::new(&order, Vec::new());
+        }
 
-                    if order.is_filled() {
-                        break;
-                    }
-                }
+        match order.kind() {
+            OrderKind::Limit | OrderKind::Pegged { .. } => self.match_limit_order(order),
+            _ => self.match_market_order(order),
+        }
+    }
 
-                if !order.is_filled() {
-                    if *order.order_type() == OrderType::LimitBuy {
-                        self.buy_volume += order.quantity();
-                        self.buy_orders
-                            .entry(order.price().unwrap())
-                            .or_insert(VecDeque::new())
-                            .push_back(order);
-                    } else {
-                        self.sell_volume += order.quantity();
-                        self.sell_orders
-                            .entry(order.price().unwrap())
-                            .or_insert(VecDeque::new())
-                            .push_back(order);
-                    }
-                }
+    // lets the book come to rest after a change, triggering the stops the last trade reached
+    // and moving pegged orders with the top of book until neither changes anything
+    fn settle(&mut self) -> Vec<ExecutionReport> {
+        let mut triggered = Vec::new();
+        loop {
+            self.apply_oco_fills();
+            // stops wait out an auction or a halt, the uncross fires them
+            if self.phase == TradingPhase::Continuous {
+                triggered.extend(self.run_triggers());
+            }
+            if !self.reprice_pegged_orders() {
+                return triggered;
             }
         }
     }
 
-    pub fn cancel_order(&mut self, order_id: String) -> Result<(), String> {
-        let (_, order) = self
-            .order_index
-            .remove_entry(&order_id)
-            .ok_or("Invalid Order Id".to_string())?;
+    // best displayed prices pegs follow, hidden orders and other pegs don't count
+    fn peg_reference(&self) -> (Option<Decimal>, Option<Decimal>) {
+        let is_reference = |(_, orders): &(&Decimal, &VecDeque<Order>)| {
+            orders
+                .iter()
+                .any(|order| !order.is_hidden() && !order.kind().is_pegged())
+        };
 
-        let orders = if *order.order_type() == OrderType::LimitBuy {
-            self.buy_volume -= order.quantity();
-            self.buy_orders.get_mut(&order.price().unwrap())
-        } else {
-            self.sell_volume -= order.quantity();
-            self.sell_orders.get_mut(&order.price().unwrap())
+        let bid = self.buy_orders.iter().rev().find(is_reference);
+        let ask = self.sell_orders.iter().find(is_reference);
+        (bid.map(|(price, _)| *price), ask.map(|(price, _)| *price))
+    }
+
+    // where a pegged order belongs right now, None while its reference side is empty
+    fn peg_price(&self, order: &Order) -> Option<Decimal> {
+        let OrderKind::Pegged {
+            reference,
+            offset,
+            cap,
+        } = *order.kind()
+        else {
+            return None;
         };
 
-        let orders = orders.ok_or("Order already executed".to_string())?;
-        orders.retain(|o| *o.id() != order_id);
+        let (bid, ask) = self.peg_reference();
+        let side = *order.side();
+        let reference_price = match (reference, side) {
+            (PegReference::Primary, Side::Buy) | (PegReference::Market, Side::Sell) => bid?,
+            (PegReference::Primary, Side::Sell) | (PegReference::Market, Side::Buy) => ask?,
+            (PegReference::Midpoint, _) => (bid? + ask?) / dec!(2),
+        };
 
-        Ok(())
+        let price = match (side, cap) {
+            (Side::Buy, Some(cap)) => (reference_price - offset).min(cap),
+            (Side::Buy, None) => reference_price - offset,
+            (Side::Sell, Some(cap)) => (reference_price + offset).max(cap),
+            (Side::Sell, None) => reference_price + offset,
+        };
+        let price = self.rules.round_to_tick(price, side);
+        (price > Decimal::ZERO).then_some(price)
     }
 
-    // only limit order can be converted to market order or limit order parameters can be updated
-    pub fn update_order(
-        &mut self,
-        order_id: String,
-        quantity: Option<f64>,
-        order_type: Option<OrderType>,
-        price: Option<f64>,
-    ) -> Result<(), String> {
-        let (_, order) = self
-            .order_index
-            .remove_entry(&order_id)
-            .ok_or("Invalid Order Id".to_string())?;
-
-        let orders = if matches!(*order.order_type(), OrderType::LimitBuy | OrderType::Buy) {
-            self.buy_orders.get_mut(&order.price().unwrap())
-        } else {
-            self.sell_orders.get_mut(&order.price().unwrap())
-        };
+    // requeues every resting peg whose price moved at the back of its new level, matching it
+    // if it became marketable; a peg keeps its price while its reference side is empty
+    fn reprice_pegged_orders(&mut self) -> bool {
+        let pegged: Vec<String> = self
+            .buy_orders
+            .values()
+            .chain(self.sell_orders.values())
+            .flatten()
+            .filter(|order| order.kind().is_pegged())
+            .map(|order| order.id().clone())
+            .collect();
+
+        let mut moved = false;
+        for order_id in pegged {
+            // an earlier peg may have traded this one away
+            let Some(order) = self.order_index.get(&order_id) else {
+                continue;
+            };
+            let old_price = order.price().unwrap();
+            let Some(new_price) = self.peg_price(order).filter(|price| *price != old_price) else {
+                continue;
+            };
+            let Some(mut order) = self.take_resting(&order_id) else {
+                continue;
+            };
+
+            order.reprice(new_price);
+            self.history.record(&order, OrderEventKind::Amended);
+            let report = self.match_limit_order(order);
+            self.peg_amends.push(PegAmend {
+                order_id,
+                old_price,
+                new_price,
+                report,
+            });
+            moved = true;
+        }
 
-        let orders = orders.ok_or("Order already executed".to_string())?;
+        moved
+    }
 
-        if let Some(pos) = orders.iter().position(|order| *order.id() == order_id) {
-            let mut order = orders.remove(pos).unwrap(); // Remove and get the order
-            if *order.order_type() == OrderType::LimitBuy {
-                self.buy_volume -= order.quantity();
-            }
-            if *order.order_type() == OrderType::LimitSell {
-                self.sell_volume -= order.quantity();
-            }
+    // buy stops trigger once the market trades at or above them, sell stops at or below
+    fn is_triggered(&self, side: Side, stop_price: Decimal) -> bool {
+        match side {
+            Side::Buy => self.last_traded_price >= stop_price,
+            Side::Sell => self.last_traded_price <= stop_price,
+        }
+    }
 
-            let updated_order = order.update(order_type, price, quantity)?;
-            self.order_index.insert(order.id().clone(), order.clone());
+    // trailing trigger for the current last traded price, sell stops trail below it, buy stops above
+    fn trail_level(&self, side: Side, offset: TrailingOffset) -> Decimal {
+        let offset = offset.amount(self.last_traded_price);
+        match side {
+            Side::Buy => self.last_traded_price + offset,
+            Side::Sell => self.last_traded_price - offset,
+        }
+    }
 
-            if matches!(
-                updated_order.order_type(),
-                OrderType::LimitBuy | OrderType::LimitSell
-            ) {
-                orders.push_back(order.clone());
+    // moves trailing stops along with the last traded price, only ever towards the market
+    fn ratchet_trailing_stops(&mut self) {
+        let mut moves = Vec::new();
+        for (side, stops) in [(Side::Buy, &self.buy_stops), (Side::Sell, &self.sell_stops)] {
+            for (trigger_price, orders) in stops.iter() {
+                for order in orders.iter() {
+                    let Some(offset) = order.kind().trailing_offset() else {
+                        continue;
+                    };
+                    let level = self.trail_level(side, offset);
+                    let favourable = match side {
+                        Side::Buy => level < *trigger_price,
+                        Side::Sell => level > *trigger_price,
+                    };
+                    if favourable {
+                        moves.push((order.id().clone(), level));
+                    }
+                }
             }
+        }
 
-            if matches!(
-                *updated_order.order_type(),
-                OrderType::LimitBuy | OrderType::LimitSell
-            ) {
-                if *updated_order.order_type() == OrderType::LimitBuy {
-                    self.buy_volume += order.quantity();
-                } else {
-                    self.sell_volume += order.quantity();
-                }
+        for (order_id, level) in moves {
+            let Some(mut order) = self.take_resting(&order_id) else {
+                continue;
+            };
+            order.set_trail_price(level);
+            self.order_index.insert(order_id, order.clone());
+
+            let stops = match order.side() {
+                Side::Buy => &mut self.buy_stops,
+                Side::Sell => &mut self.sell_stops,
+            };
+            stops.entry(level).or_default().push_back(order);
+        }
+    }
 
-                self.match_limit_order(order);
-            } else {
-                self.match_market_order(order);
-            }
+    fn park_stop_order(&mut self, order: Order, stop_price: Decimal) -> ExecutionReport {
+        let report = ExecutionReport::new(&order, Vec::new());
+        self.order_index.insert(order.id().clone(), order.clone());
+
+        if let TimeInForce::Gtd(expiry) = *order.time_in_force() {
+            self.expiries.insert((expiry, order.id().clone()));
         }
-        Ok(())
+        let stops = match order.side() {
+            Side::Buy => &mut self.buy_stops,
+            Side::Sell => &mut self.sell_stops,
+        };
+        stops.entry(stop_price).or_default().push_back(order);
+
+        report
     }
 
-   
-    pub fn get_order(&self, order_id: String) -> Option<&Order> {
-        self.order_index.get(&order_id)
+    // converts every stop the last traded price has reached until none is left, so cascades
+    // settle within the call; buy stops go first, lowest stop price first, then sell stops,
+    // highest stop price first, each level in arrival order
+    fn run_triggers(&mut self) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+
+        self.ratchet_trailing_stops();
+        // a stop whose trades trip the band leaves the rest parked for the reopening
+        while self.phase == TradingPhase::Continuous {
+            let Some(mut order) = self.next_triggered_stop() else {
+                break;
+            };
+            self.trigger(&mut order);
+            reports.push(self.process_order(order));
+            self.apply_oco_fills();
+            self.ratchet_trailing_stops();
+        }
+
+        reports
     }
 
+    // converts a stop into the order it carries, a limit worked out from a trailing level is
+    // put on the tick
+    fn trigger(&mut self, order: &mut Order) {
+        order.trigger();
+        if let Some(price) = *order.price() {
+            order.reprice(self.rules.round_to_tick(price, *order.side()));
+        }
+        self.history.record(order, OrderEventKind::Triggered);
+    }
 
-}
+    fn next_triggered_stop(&mut self) -> Option<Order> {
+        let last_traded_price = self.last_traded_price;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let level = match self.buy_stops.first_entry() {
+            Some(level) if *level.key() <= last_traded_price => Some(level),
+            _ => match self.sell_stops.last_entry() {
+                Some(level) if *level.key() >= last_traded_price => Some(level),
+                _ => None,
+            },
+        };
 
-    #[test]
-    pub fn pass_add_order() {
-        let listing_price = 1023.0;
-        let pair_id = String::from("ETHINC");
-        let sell_quantity = 1232.5;
-        let buy_quantity = 23243.5;
+        let mut level = level?;
+        let order = level.get_mut().pop_front();
+        if level.get().is_empty() {
+            level.remove();
+        }
+        order
+    }
 
-        let mut book = OrderBook::new(pair_id, listing_price);
+    fn match_market_order(&mut self, mut order: Order) -> ExecutionReport {
+        let fills = self.sweep(&mut order, None);
 
-        let order_id = book
-            .add_order(
-                OrderType::LimitBuy,
-                Some(listing_price + 123.2),
-                buy_quantity,
-            )
-            .expect("can't add limit buy with price");
+        if order.is_filled() && *order.status() != OrderStatus::Cancelled {
+            self.order_index.remove(order.id());
+            return ExecutionReport::new(&order, fills);
+        }
 
-        assert_eq!(book.buy_volume, buy_quantity);
+        // market orders never rest, the unfilled remainder is cancelled and kept queryable
+        order.cancel_remainder();
+        self.history.record(&order, OrderEventKind::Cancelled);
+        self.order_index.insert(order.id().clone(), order.clone());
+        ExecutionReport::new(&order, fills)
+    }
 
-        let order = book.get_order(order_id).unwrap();
+    // best displayed opposite price a limit order's price would cross, hidden liquidity is
+    // never given away and simply trades if reached
+    fn crossed_price(&self, order: &Order) -> Option<Decimal> {
+        let price = (*order.price())?;
+        match order.side() {
+            Side::Buy => self.best_ask().filter(|ask| *ask <= price),
+            Side::Sell => self.best_bid().filter(|bid| *bid >= price),
+        }
+    }
+
+    fn match_limit_order(&mut self, mut order: Order) -> ExecutionReport {
+        if let Some(post_only) = *order.post_only() {
+            if let Some(best_opposite) = self.crossed_price(&order) {
+                match post_only {
+                    PostOnly::Reject => {
+                        order.reject();
+                        self.history.record(&order, OrderEventKind::Rejected);
+                        self.order_index.remove(order.id());
+                        return ExecutionReport::rejected(&order, RejectReason::PostOnlyWouldCross);
+                    }
+                    PostOnly::Reprice => {
+                        // steps one tick behind, or a cent where the pair sets no tick
+                        let step = self
+                            .rules
+                            .tick_size(best_opposite)
+                            .unwrap_or(DEFAULT_REPRICE_STEP);
+                        match order.side() {
+                            Side::Buy => order.reprice(best_opposite - step),
+                            Side::Sell => order.reprice(best_opposite + step),
+                        }
+                    }
+                }
+            }
+        }
 
-        assert_eq!(*order.order_type(), OrderType::LimitBuy);
+        let limit_price = order.price().unwrap();
+        let fills = self.sweep(&mut order, Some(limit_price));
+        let report = ExecutionReport::new(&order, fills);
+        // self-trade prevention may have cancelled the order mid sweep
+        let cancelled = *order.status() == OrderStatus::Cancelled;
 
-        book.add_order(OrderType::LimitSell, Some(listing_price), sell_quantity)
-            .expect("Unable to add a limit sell");
-        assert_eq!(book.sell_volume, sell_quantity);
+        if order.is_filled() && !cancelled {
+            self.order_index.remove(order.id());
+            return report;
+        }
+
+        if cancelled || matches!(order.time_in_force(), TimeInForce::Ioc | TimeInForce::Fok) {
+            order.cancel_remainder();
+            self.history.record(&order, OrderEventKind::Cancelled);
+            self.order_index.insert(order.id().clone(), order.clone());
+            return ExecutionReport::new(&order, report.fills);
+        }
+
+        // rest the remainder on its own side, an iceberg shows a full slice of what is left
+        order.refresh();
+        if let TimeInForce::Gtd(expiry) = *order.time_in_force() {
+            self.expiries.insert((expiry, order.id().clone()));
+        }
+        self.order_index.insert(order.id().clone(), order.clone());
+        match order.side() {
+            Side::Buy => {
+                self.buy_volume += order.quantity();
+                enqueue(self.buy_orders.entry(limit_price).or_default(), order);
+            }
+            Side::Sell => {
+                self.sell_volume += order.quantity();
+                enqueue(self.sell_orders.entry(limit_price).or_default(), order);
+            }
+        }
+        report
     }
 
-    #[test]
-    pub fn pass_update_order() {
-        let listing_price = 1023.0;
-        let pair_id = String::from("ETHINC");
-        let buy_quantity = 23243.5;
+    // walks the opposite side from the best price, stopping at the limit price if one is given
+    fn sweep(&mut self, order: &mut Order, limit_price: Option<Decimal>) -> Vec<Fill> {
+        let side = *order.side();
+        let is_buy = side == Side::Buy;
+        let mut fills: Vec<Fill> = Vec::new();
 
-        let mut book = OrderBook::new(pair_id, listing_price);
+        // orders only accumulate during an auction or a halt
+        if self.phase != TradingPhase::Continuous {
+            return fills;
+        }
 
-        let order_id = book
-            .add_order(
-                OrderType::LimitBuy,
-                Some(listing_price + 123.2),
-                buy_quantity,
-            )
-            .expect("can't add limit buy with price");
+        let (orders, volume) = if is_buy {
+            (&mut self.sell_orders, &mut self.sell_volume)
+        } else {
+            (&mut self.buy_orders, &mut self.buy_volume)
+        };
 
-        let order = book.get_order(order_id.clone()).unwrap();
-        assert_eq!(book.buy_volume, buy_quantity);
-        assert_eq!(order.quantity(), buy_quantity);
+        while !order.is_filled() && *order.status() != OrderStatus::Cancelled {
+            let best = if is_buy {
+                orders.first_entry()
+            } else {
+                orders.last_entry()
+            };
+            let Some(mut level) = best else {
+                break;
+            };
+
+            let crosses = match limit_price {
+                None => true,
+                Some(limit) if is_buy => *level.key() <= limit,
+                Some(limit) => *level.key() >= limit,
+            };
+            if !crosses {
+                break;
+            }
 
-        let buy_quantity = buy_quantity - 100.0;
+            // a trade outside the band never prints, it trips the circuit breaker instead
+            let level_price = *level.key();
+            if let Some(band) = self.price_band {
+                if band.is_breached(self.last_traded_price, level_price) {
+                    self.phase = band.breach_phase();
+                    self.resume_at = Some(Utc::now().timestamp() + band.duration);
+                    break;
+                }
+            }
 
-        book.update_order(
-            order_id.clone(),
-            Some(buy_quantity),
-            Some(OrderType::Buy),
-            None,
-        )
-        .expect("Update order failed");
+            let level_orders = level.get_mut();
+            // pro-rata policies share the incoming quantity across the level up front, FIFO
+            // lets the queue decide
+            let mut allocations = allocate(
+                self.allocation_policy,
+                &self.rules,
+                self.quantity_scale,
+                level_orders,
+                order.quantity(),
+            );
+
+            let mut pos = 0;
+            while let Some(book_order) = level_orders.get_mut(pos) {
+                if order.is_self_trade(book_order) {
+                    let (cancel_taker, cancel_maker) = match order.self_trade_prevention() {
+                        SelfTradePrevention::CancelNewest => (true, false),
+                        SelfTradePrevention::CancelOldest => (false, true),
+                        SelfTradePrevention::CancelBoth => (true, true),
+                        SelfTradePrevention::DecrementAndCancel => {
+                            let quantity = order.quantity().min(book_order.quantity());
+                            order.reduce(quantity);
+                            book_order.reduce(quantity);
+                            *volume -= quantity;
+                            for reduced in [&*order, &*book_order] {
+                                if !reduced.is_filled() {
+                                    self.history.record(reduced, OrderEventKind::Amended);
+                                }
+                            }
+                            (order.is_filled(), book_order.is_filled())
+                        }
+                    };
+
+                    if cancel_maker {
+                        let mut book_order = level_orders.remove(pos).unwrap();
+                        *volume -= book_order.quantity();
+                        book_order.cancel_remainder();
+                        self.history.record(&book_order, OrderEventKind::Cancelled);
+                        self.order_index.insert(book_order.id().clone(), book_order);
+                    } else {
+                        self.order_index
+                            .insert(book_order.id().clone(), book_order.clone());
+                        pos += 1;
+                    }
 
-        let order = book.get_order(order_id.clone()).unwrap();
+                    if cancel_taker {
+                        order.cancel_remainder();
+                        break;
+                    }
+                    continue;
+                }
 
-        // once market order is converted to limit order, it is removed from orderbook
-        assert_eq!(book.buy_volume, 0.0);
-        assert_eq!(order.quantity(), buy_quantity);
-    }
+                let allocation = match allocations.as_ref() {
+                    Some(allocations) => allocations.get(book_order.id()).copied(),
+                    None => Some(book_order.visible_quantity()),
+                };
+                // a quote budget that can't pay for another lot here won't at worse prices
+                let affordable = match order.quote_budget() {
+                    Some(budget) => self
+                        .rules
+                        .round_to_lot(budget / level_price, self.quantity_scale),
+                    None => order.quantity(),
+                };
+                if affordable.is_zero() {
+                    order.cancel_remainder();
+                    break;
+                }
 
-    #[test]
-    pub fn pass_cancel_order() {
-        let listing_price = 1023.0;
-        let pair_id = String::from("ETHINC");
-        let buy_quantity = 23243.5;
+                let traded_quantity = order
+                    .quantity()
+                    .min(book_order.visible_quantity())
+                    .min(allocation.unwrap_or_default())
+                    .min(affordable);
+                if traded_quantity.is_zero() {
+                    pos += 1;
+                    continue;
+                }
+                if let Some(allocation) = allocations
+                    .as_mut()
+                    .and_then(|allocations| allocations.get_mut(book_order.id()))
+                {
+                    *allocation -= traded_quantity;
+                }
 
-        let mut book = OrderBook::new(pair_id, listing_price);
+                book_order.fill_order(traded_quantity);
+                order.fill_order(traded_quantity);
+                self.history.record_fill(book_order, traded_quantity);
+                self.history.record_fill(order, traded_quantity);
+                order.spend_quote(traded_quantity * level_price);
 
-        let order_id = book
-            .add_order(
-                OrderType::LimitBuy,
-                Some(listing_price + 123.2),
-                buy_quantity,
-            )
-            .expect("can't add limit buy with price");
+                for order_id in [book_order.id(), order.id()] {
+                    if self.oco_legs.contains_key(order_id) {
+                        self.oco_fills.push((order_id.clone(), traded_quantity));
+                    }
+                }
 
-        book.cancel_order(order_id.clone())
-            .expect("Failled cancelling order");
+                *volume -= traded_quantity;
 
-        // once market order is converted to limit order, it is removed from orderbook
-        assert_eq!(book.buy_volume, 0.0);
-        assert!(book.get_order(order_id.clone()).is_none());
+                let timestamp = Utc::now().timestamp();
+                self.last_traded_price = level_price;
+                self.last_traded_size = traded_quantity;
+                self.trade_window
+                    .record(timestamp, level_price, traded_quantity);
+
+                fills.push(Fill {
+                    trade_id: self.id_generator.generate_trade_id(),
+                    maker_order_id: book_order.id().clone(),
+                    taker_order_id: order.id().clone(),
+                    price: level_price,
+                    quantity: traded_quantity,
+                    aggressor_side: side,
+                    timestamp,
+                });
+
+                if book_order.is_filled() {
+                    self.order_index.remove(book_order.id());
+                    level_orders.remove(pos);
+                } else if book_order.needs_refresh() {
+                    // iceberg reserve shows a new slice and loses time priority
+                    let mut book_order = level_orders.remove(pos).unwrap();
+                    book_order.refresh();
+                    self.order_index
+                        .insert(book_order.id().clone(), book_order.clone());
+                    enqueue(level_orders, book_order);
+                } else {
+                    self.order_index
+                        .insert(book_order.id().clone(), book_order.clone());
+                    pos += 1;
+                }
+
+                if order.is_filled() {
+                    break;
+                }
+            }
+
+            // prune emptied levels so the best price is always the first/last key
+            if level_orders.is_empty() {
+                level.remove();
+            }
+        }
+
+        fills
     }
 
-    #[test]
-    pub fn pass_match_limit_orders() {
-        let listing_price = 1023.0;
-        let pair_id = String::from("ETHINC");
-        let quantity = 23243.5;
+    // walks the opposite side the way a sweep would, telling whether the order fills before
+    // it runs out of crossing liquidity, self-trade prevention cancels it or a level outside
+    // the price band trips the breaker
+    fn fills_completely(&self, order: &Order) -> bool {
+        let side = *order.side();
+        let limit_price = *order.price();
+        let levels: Box<dyn Iterator<Item = (&Decimal, &VecDeque<Order>)>> = match side {
+            Side::Buy => Box::new(self.sell_orders.iter()),
+            Side::Sell => Box::new(self.buy_orders.iter().rev()),
+        };
+
+        let mut left = order.quantity();
+        // each trade moves the reference the band is measured from
+        let mut reference_price = self.last_traded_price;
+        let crossing = levels.take_while(|(price, _)| match (side, limit_price) {
+            (_, None) => true,
+            (Side::Buy, Some(limit)) => **price <= limit,
+            (Side::Sell, Some(limit)) => **price >= limit,
+        });
+        for (price, orders) in crossing {
+            if self
+                .price_band
+                .is_some_and(|band| band.is_breached(reference_price, *price))
+            {
+                return false;
+            }
+            for book_order in orders {
+                if order.is_self_trade(book_order) {
+                    // only cancelling the resting order lets the sweep carry on
+                    if *order.self_trade_prevention() != SelfTradePrevention::CancelOldest {
+                        return false;
+                    }
+                    continue;
+                }
+                left -= book_order.quantity();
+                reference_price = *price;
+                if left <= Decimal::ZERO {
+                    return true;
+                }
+            }
+        }
+        false
+    }
 
-        let mut book = OrderBook::new(pair_id, listing_price);
+    // the levels holding a resting or parked order, the side volume it counts towards and its
+    // level price
+    fn locate(
+        &mut self,
+        order_id: &String,
+    ) -> Option<(&mut PriceLevels, Option<&mut Decimal>, Decimal)> {
+        let order = self.order_index.get(order_id)?;
+
+        let located = match (order.side(), order.trigger_price()) {
+            (Side::Buy, Some(stop_price)) => (&mut self.buy_stops, None, stop_price),
+            (Side::Sell, Some(stop_price)) => (&mut self.sell_stops, None, stop_price),
+            (Side::Buy, None) => (
+                &mut self.buy_orders,
+                Some(&mut self.buy_volume),
+                (*order.price())?,
+            ),
+            (Side::Sell, None) => (
+                &mut self.sell_orders,
+                Some(&mut self.sell_volume),
+                (*order.price())?,
+            ),
+        };
+        Some(located)
+    }
 
-        book.add_order(OrderType::LimitBuy, Some(listing_price), quantity)
-            .expect("can't add limit buy with price");
+    // removes a resting or parked order from its price level, keeping volumes and empty levels
+    // in check
+    fn take_resting(&mut self, order_id: &String) -> Option<Order> {
+        let (orders, volume, price) = self.locate(order_id)?;
 
-        book.add_order(OrderType::LimitSell, Some(listing_price), quantity)
-            .expect("Can't add limit sell order");
+        let level = orders.get_mut(&price)?;
+        let pos = level.iter().position(|o| o.id() == order_id)?;
+        let order = level.remove(pos).unwrap();
+        if level.is_empty() {
+            orders.remove(&price);
+        }
 
-        dbg!(&book);
+        if let Some(volume) = volume {
+            *volume -= order.quantity();
+        }
+        Some(order)
+    }
 
-        assert_eq!(book.buy_volume, 0.0);
-        assert_eq!(book.sell_volume, 0.0);
+    // takes quantity off a resting or parked order where it stands, returning what is left
+    fn reduce_resting(&mut self, order_id: &String, amount: Decimal) -> Option<Decimal> {
+        let (orders, volume, price) = self.locate(order_id)?;
+
+        let order = orders
+            .get_mut(&price)?
+            .iter_mut()
+            .find(|order| order.id() == order_id)?;
+        let amount = amount.min(order.quantity());
+        order.reduce(amount);
+        if let Some(volume) = volume {
+            *volume -= amount;
+        }
+
+        let order = order.clone();
+        let remaining = order.quantity();
+        self.order_index.insert(order_id.clone(), order);
+        Some(remaining)
+    }
+
+    fn check_live(&self, order_id: &String) -> Result<(), MatcherError> {
+        match self.order_index.get(order_id).map(|order| *order.status()) {
+            None => Err(MatcherError::UnknownOrder(order_id.clone())),
+            Some(OrderStatus::Cancelled) => {
+                Err(MatcherError::OrderAlreadyCancelled(order_id.clone()))
+            }
+            Some(OrderStatus::Expired) => Err(MatcherError::OrderNotCancellable(order_id.clone())),
+            Some(_) => Ok(()),
+        }
+    }
+
+    // takes a user-cancelled order out of the book and the index, an order that isn't in a
+    // level is left alone
+    fn withdraw(&mut self, order_id: &String) -> Option<Order> {
+        let mut order = self.take_resting(order_id)?;
+        self.order_index.remove(order_id);
+        order.cancel_remainder();
+        self.history.record(&order, OrderEventKind::Cancelled);
+
+        // the sibling of a cancelled leg carries on as a plain order
+        if let Some((group_id, _)) = self.oco_legs.get(order_id).cloned() {
+            self.unlink_oco(&group_id);
+        }
+        Some(order)
+    }
+
+    pub fn cancel_order(&mut self, order_id: String) -> Result<(), MatcherError> {
+        self.check_live(&order_id)?;
+
+        match self.withdraw(&order_id) {
+            Some(_) => {
+                self.settle();
+                Ok(())
+            }
+            None => Err(MatcherError::OrderAlreadyExecuted(order_id)),
+        }
+    }
+
+    // cancels every resting and parked order the filter selects, returning their ids
+    pub fn mass_cancel(&mut self, filter: &CancelFilter) -> Vec<String> {
+        let selected: Vec<String> = [
+            &self.buy_orders,
+            &self.sell_orders,
+            &self.buy_stops,
+            &self.sell_stops,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|(price, level)| {
+            level
+                .iter()
+                .filter(|order| filter.matches(order, *price))
+                .map(|order| order.id().clone())
+        })
+        .collect();
+
+        let cancelled: Vec<String> = selected
+            .into_iter()
+            .filter(|order_id| self.withdraw(order_id).is_some())
+            .collect();
+
+        if !cancelled.is_empty() {
+            self.settle();
+        }
+        cancelled
+    }
+
+    // expires every resting GTD order whose expiry has passed, returning their ids
+    pub fn expire_orders(&mut self, now: i64) -> Vec<String> {
+        let mut expired = Vec::new();
+
+        while let Some((expiry, _)) = self.expiries.first() {
+            if *expiry > now {
+                break;
+            }
+            let (_, order_id) = self.expiries.pop_first().unwrap();
+
+            let is_live = self
+                .order_index
+                .get(&order_id)
+                .is_some_and(|order| order.is_expired(now));
+            if !is_live {
+                continue;
+            }
+
+            if let Some(mut order) = self.take_resting(&order_id) {
+                order.expire();
+                self.history.record(&order, OrderEventKind::Expired);
+                self.order_index.insert(order_id.clone(), order);
+                expired.push(order_id);
+            }
+        }
+
+        if !expired.is_empty() {
+            self.settle();
+        }
+        expired
+    }
+
+    // purges every resting DAY order at the end of the trading session, returning their ids
+    pub fn end_session(&mut self) -> Vec<String> {
+        let day_orders: Vec<String> = self
+            .buy_orders
+            .values()
+            .chain(self.sell_orders.values())
+            .chain(self.buy_stops.values())
+            .chain(self.sell_stops.values())
+            .flatten()
+            .filter(|order| *order.time_in_force() == TimeInForce::Day)
+            .map(|order| order.id().clone())
+            .collect();
+
+        for order_id in day_orders.iter() {
+            if let Some(mut order) = self.take_resting(order_id) {
+                order.expire();
+                self.history.record(&order, OrderEventKind::Expired);
+                self.order_index.insert(order_id.clone(), order);
+            }
+        }
+
+        self.settle();
+        day_orders
+    }
+
+    // cancel/replace of a live order: a smaller quantity keeps its place in the queue, a new
+    // price, kind or a larger quantity requeues it at the back of its new level and matches it
+    // if it became marketable; nothing changes unless the whole amend is valid
+    pub fn update_order(
+        &mut self,
+        order_id: String,
+        quantity: Option<Decimal>,
+        kind: Option<OrderKind>,
+        price: Option<Decimal>,
+    ) -> Result<ExecutionReport, MatcherError> {
+        self.resume_trading(Utc::now().timestamp());
+        if let Some(quantity) = quantity {
+            self.check_quantity_scale(quantity)?;
+        }
+        self.check_live(&order_id)?;
+
+        // parked stops are not in the price levels, and orders can't be turned into stops
+        let current = self.order_index[&order_id].clone();
+        if current.kind().is_stop() || kind.is_some_and(|kind| kind.is_stop()) {
+            return Err(MatcherError::OrderNotAmendable(order_id));
+        }
+
+        let mut replacement = current.clone();
+        replacement.update(kind, price, quantity)?;
+        self.check_rules(&replacement)?;
+
+        let keeps_priority = replacement.kind() == current.kind()
+            && replacement.price() == current.price()
+            && replacement.quantity() <= current.quantity();
+
+        // like a cancel, taking quantity off goes through in any phase
+        if keeps_priority {
+            self.reduce_resting(&order_id, current.quantity() - replacement.quantity())
+                .ok_or_else(|| MatcherError::OrderAlreadyExecuted(order_id.clone()))?;
+            let order = &self.order_index[&order_id];
+            self.history.record(order, OrderEventKind::Amended);
+            return Ok(ExecutionReport::new(order, Vec::new()));
+        }
+
+        self.check_phase(replacement.kind(), replacement.time_in_force())?;
+
+        // a requeued peg follows the current top of book, or stays put without one
+        let _ = self.peg_entry(&mut replacement);
+
+        // a post-only replacement that would cross is turned away with the original in place
+        if *replacement.post_only() == Some(PostOnly::Reject)
+            && self.crossed_price(&replacement).is_some()
+        {
+            return Ok(ExecutionReport::rejected(
+                &current,
+                RejectReason::PostOnlyWouldCross,
+            ));
+        }
+
+        self.take_resting(&order_id)
+            .ok_or_else(|| MatcherError::OrderAlreadyExecuted(order_id.clone()))?;
+        self.history.record(&replacement, OrderEventKind::Amended);
+
+        let mut report = self.process_order(replacement);
+        report.triggered = self.settle();
+        Ok(report)
+    }
+
+    fn check_quantity_scale(&self, quantity: Decimal) -> Result<(), MatcherError> {
+        if quantity.normalize().scale() > self.quantity_scale {
+            return Err(MatcherError::QuantityPrecision {
+                max_scale: self.quantity_scale,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn set_rules(&mut self, rules: InstrumentRules) {
+        self.rules = rules;
+    }
+
+    pub fn set_allocation_policy(&mut self, allocation_policy: AllocationPolicy) {
+        self.allocation_policy = allocation_policy;
+    }
+
+    // stops continuous matching, orders rest where they are until the uncross
+    pub fn start_auction(&mut self) {
+        self.phase = TradingPhase::Auction;
+    }
+
+    pub fn set_price_band(&mut self, price_band: Option<PriceBand>) {
+        self.price_band = price_band;
+    }
+
+    // reopens a book whose circuit breaker period has run out through an uncross
+    pub fn resume_trading(&mut self, now: i64) -> Option<UncrossReport> {
+        if self.resume_at.is_some_and(|resume_at| resume_at <= now) {
+            return Some(self.uncross());
+        }
+        None
+    }
+
+    pub fn phase(&self) -> TradingPhase {
+        self.phase
+    }
+
+    // equilibrium the auction would uncross at right now, over full quantities including
+    // hidden orders and iceberg reserves
+    pub fn indicative_uncross(&self) -> Option<IndicativeUncross> {
+        let levels = |orders: &PriceLevels| -> Vec<(Decimal, Decimal)> {
+            orders
+                .iter()
+                .map(|(price, orders)| (*price, orders.iter().map(|order| order.quantity()).sum()))
+                .collect()
+        };
+
+        equilibrium(
+            &levels(&self.buy_orders),
+            &levels(&self.sell_orders),
+            self.last_traded_price,
+        )
+    }
+
+    // ends the auction, executing the crossing orders of both sides in price-time priority at
+    // the single equilibrium price and returning to continuous matching; an uncross has no
+    // aggressor, its trades report the buy order as taker
+    pub fn uncross(&mut self) -> UncrossReport {
+        while let Some((buy_id, sell_id)) = self.uncross_self_trade() {
+            self.prevent_self_trade(&buy_id, &sell_id);
+        }
+        let uncross = self.indicative_uncross();
+        self.phase = TradingPhase::Continuous;
+        self.resume_at = None;
+
+        let mut fills = Vec::new();
+        if let Some(uncross) = uncross {
+            let mut left = uncross.volume;
+            while !left.is_zero() {
+                let (Some(mut bids), Some(mut asks)) =
+                    (self.buy_orders.last_entry(), self.sell_orders.first_entry())
+                else {
+                    break;
+                };
+
+                let buy = bids.get_mut().front_mut().unwrap();
+                let sell = asks.get_mut().front_mut().unwrap();
+                let quantity = left.min(buy.quantity()).min(sell.quantity());
+
+                buy.fill_order(quantity);
+                sell.fill_order(quantity);
+                self.history.record_fill(buy, quantity);
+                self.history.record_fill(sell, quantity);
+                left -= quantity;
+                self.buy_volume -= quantity;
+                self.sell_volume -= quantity;
+
+                for order_id in [buy.id(), sell.id()] {
+                    if self.oco_legs.contains_key(order_id) {
+                        self.oco_fills.push((order_id.clone(), quantity));
+                    }
+                }
+
+                let timestamp = Utc::now().timestamp();
+                self.last_traded_price = uncross.price;
+                self.last_traded_size = quantity;
+                self.trade_window.record(timestamp, uncross.price, quantity);
+
+                fills.push(Fill {
+                    trade_id: self.id_generator.generate_trade_id(),
+                    maker_order_id: sell.id().clone(),
+                    taker_order_id: buy.id().clone(),
+                    price: uncross.price,
+                    quantity,
+                    aggressor_side: Side::Buy,
+                    timestamp,
+                });
+
+                for mut level in [bids, asks] {
+                    let orders = level.get_mut();
+                    let order = orders.front_mut().unwrap();
+                    if order.is_filled() {
+                        self.order_index.remove(order.id());
+                        orders.pop_front();
+                    } else {
+                        order.refresh();
+                        self.order_index.insert(order.id().clone(), order.clone());
+                    }
+                    if orders.is_empty() {
+                        level.remove();
+                    }
+                }
+            }
+        }
+
+        UncrossReport {
+            price: uncross.map(|uncross| uncross.price),
+            volume: fills.iter().map(|fill| fill.quantity).sum(),
+            fills,
+            triggered: self.settle(),
+        }
+    }
+
+    // first buy and sell of the same owner the uncross would pair, walking both sides in
+    // price-time priority the way the uncross executes
+    fn uncross_self_trade(&self) -> Option<(String, String)> {
+        let uncross = self.indicative_uncross()?;
+        let mut bids = self.buy_orders.values().rev().flatten();
+        let mut asks = self.sell_orders.values().flatten();
+
+        let (mut buy, mut sell) = (bids.next()?, asks.next()?);
+        let (mut buy_left, mut sell_left) = (buy.quantity(), sell.quantity());
+        let mut left = uncross.volume;
+        while !left.is_zero() {
+            if buy.is_self_trade(sell) {
+                return Some((buy.id().clone(), sell.id().clone()));
+            }
+            let quantity = left.min(buy_left).min(sell_left);
+            left -= quantity;
+            buy_left -= quantity;
+            sell_left -= quantity;
+            if buy_left.is_zero() {
+                buy = bids.next()?;
+                buy_left = buy.quantity();
+            }
+            if sell_left.is_zero() {
+                sell = asks.next()?;
+                sell_left = sell.quantity();
+            }
+        }
+        None
+    }
+
+    // applies the self-trade prevention of the later of two resting orders of the same owner,
+    // as if it had just arrived
+    fn prevent_self_trade(&mut self, buy_id: &String, sell_id: &String) {
+        let (newest_id, oldest_id) = if sequence(buy_id) > sequence(sell_id) {
+            (buy_id, sell_id)
+        } else {
+            (sell_id, buy_id)
+        };
+        let newest = &self.order_index[newest_id];
+        let oldest = &self.order_index[oldest_id];
+
+        let (cancel_newest, cancel_oldest) = match newest.self_trade_prevention() {
+            SelfTradePrevention::CancelNewest => (true, false),
+            SelfTradePrevention::CancelOldest => (false, true),
+            SelfTradePrevention::CancelBoth => (true, true),
+            SelfTradePrevention::DecrementAndCancel => {
+                let quantity = newest.quantity().min(oldest.quantity());
+                let newest_left = self.reduce_resting(newest_id, quantity);
+                let oldest_left = self.reduce_resting(oldest_id, quantity);
+                for (order_id, left) in [(newest_id, newest_left), (oldest_id, oldest_left)] {
+                    if left.is_some_and(|left| !left.is_zero()) {
+                        self.history
+                            .record(&self.order_index[order_id], OrderEventKind::Amended);
+                    }
+                }
+                (
+                    newest_left.is_some_and(|left| left.is_zero()),
+                    oldest_left.is_some_and(|left| left.is_zero()),
+                )
+            }
+        };
+
+        if cancel_newest {
+            self.cancel_resting(newest_id);
+        }
+        if cancel_oldest {
+            self.cancel_resting(oldest_id);
+        }
+    }
+
+    pub fn get_order(&self, order_id: String) -> Option<&Order> {
+        self.order_index.get(&order_id)
+    }
+
+    // every step of an order's life, oldest first, including orders no longer in the book
+    pub fn order_history(&self, order_id: String) -> Option<&[OrderEvent]> {
+        self.history.get(&order_id)
+    }
+
+    // hands over the pegged order moves since the last call, oldest first
+    pub fn drain_peg_amends(&mut self) -> Vec<PegAmend> {
+        std::mem::take(&mut self.peg_amends)
+    }
+
+    pub fn last_traded_price(&self) -> Decimal {
+        self.last_traded_price
+    }
+
+    // best displayed bid, levels holding only hidden orders are skipped
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.buy_orders
+            .iter()
+            .rev()
+            .find(|(_, orders)| orders.iter().any(|order| !order.is_hidden()))
+            .map(|(price, _)| *price)
+    }
+
+    // best displayed ask, levels holding only hidden orders are skipped
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.sell_orders
+            .iter()
+            .find(|(_, orders)| orders.iter().any(|order| !order.is_hidden()))
+            .map(|(price, _)| *price)
+    }
+
+    // displayed quantity per price level for the best `levels` prices on each side
+    pub fn depth(&self, levels: usize) -> Depth {
+        let depth_level = |(price, orders): (&Decimal, &VecDeque<Order>)| DepthLevel {
+            price: *price,
+            quantity: orders.iter().map(|order| order.displayed_quantity()).sum(),
+        };
+
+        Depth {
+            bids: self
+                .buy_orders
+                .iter()
+                .rev()
+                .map(depth_level)
+                .filter(|level| !level.quantity.is_zero())
+                .take(levels)
+                .collect(),
+            asks: self
+                .sell_orders
+                .iter()
+                .map(depth_level)
+                .filter(|level| !level.quantity.is_zero())
+                .take(levels)
+                .collect(),
+        }
+    }
+
+    pub fn ticker(&self) -> Ticker {
+        self.trade_window.ticker(
+            Utc::now().timestamp(),
+            self.last_traded_price,
+            self.last_traded_size,
+            self.best_bid(),
+            self.best_ask(),
+        )
+    }
+}
+
+// queues an order at the back of its price level, hidden orders always rank behind displayed ones
+fn enqueue(level: &mut VecDeque<Order>, order: Order) {
+    if order.is_hidden() {
+        level.push_back(order);
+        return;
+    }
+
+    match level.iter().position(|queued| queued.is_hidden()) {
+        Some(pos) => level.insert(pos, order),
+        None => level.push_back(order),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::band::BreachAction;
+
+    #[test]
+    pub fn pass_add_order() {
+        let listing_price = dec!(1023);
+        let pair_id = String::from("ETHINC");
+        let sell_quantity = dec!(1232.5);
+        let buy_quantity = dec!(23243.5);
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let order_id = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(listing_price + dec!(123.2)),
+                buy_quantity,
+            )
+            .expect("can't add limit buy with price")
+            .order_id;
+
+        assert_eq!(book.buy_volume, buy_quantity);
+
+        let order = book.get_order(order_id).unwrap();
+
+        assert_eq!(*order.side(), Side::Buy);
+        assert_eq!(*order.kind(), OrderKind::Limit);
+
+        // priced above the resting bid so it rests instead of crossing
+        book.add_order(
+            Side::Sell,
+            OrderKind::Limit,
+            Some(listing_price + dec!(200)),
+            sell_quantity,
+        )
+        .expect("Unable to add a limit sell");
+        assert_eq!(book.sell_volume, sell_quantity);
+        assert_eq!(book.buy_volume, buy_quantity);
+    }
+
+    #[test]
+    pub fn pass_update_order() {
+        let listing_price = dec!(1023);
+        let pair_id = String::from("ETHINC");
+        let buy_quantity = dec!(23243.5);
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let order_id = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(listing_price + dec!(123.2)),
+                buy_quantity,
+            )
+            .expect("can't add limit buy with price")
+            .order_id;
+
+        let order = book.get_order(order_id.clone()).unwrap();
+        assert_eq!(book.buy_volume, buy_quantity);
+        assert_eq!(order.quantity(), buy_quantity);
+
+        let buy_quantity = buy_quantity - dec!(100);
+
+        book.update_order(
+            order_id.clone(),
+            Some(buy_quantity),
+            Some(OrderKind::Market),
+            None,
+        )
+        .expect("Update order failed");
+
+        let order = book.get_order(order_id.clone()).unwrap();
+
+        // once market order is converted to limit order, it is removed from orderbook
+        assert_eq!(book.buy_volume, dec!(0));
+        assert_eq!(order.quantity(), buy_quantity);
+    }
+
+    #[test]
+    pub fn pass_cancel_order() {
+        let listing_price = dec!(1023);
+        let pair_id = String::from("ETHINC");
+        let buy_quantity = dec!(23243.5);
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let order_id = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(listing_price + dec!(123.2)),
+                buy_quantity,
+            )
+            .expect("can't add limit buy with price")
+            .order_id;
+
+        book.cancel_order(order_id.clone())
+            .expect("Failled cancelling order");
+
+        // once market order is converted to limit order, it is removed from orderbook
+        assert_eq!(book.buy_volume, dec!(0));
+        assert!(book.get_order(order_id.clone()).is_none());
+    }
+
+    #[test]
+    pub fn pass_match_limit_orders() {
+        let listing_price = dec!(1023);
+        let pair_id = String::from("ETHINC");
+        let quantity = dec!(23243.5);
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(listing_price), quantity)
+            .expect("can't add limit buy with price");
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(listing_price), quantity)
+            .expect("Can't add limit sell order");
+
+        dbg!(&book);
+
+        assert_eq!(book.buy_volume, dec!(0));
+        assert_eq!(book.sell_volume, dec!(0));
+    }
+
+    #[test]
+    pub fn pass_match_limit_orders_across_prices() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let cheap_sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(5))
+            .expect("can't add limit sell")
+            .order_id;
+        let dear_sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(102)), dec!(5))
+            .expect("can't add limit sell")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(110)), dec!(5))
+            .expect("can't add limit sell");
+
+        // crosses the 100 and 102 levels, rests the remainder at 105
+        let buy = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(105)), dec!(12))
+            .expect("can't add limit buy")
+            .order_id;
+
+        assert!(book.get_order(cheap_sell).is_none());
+        assert!(book.get_order(dear_sell).is_none());
+        assert_eq!(book.sell_volume, dec!(5));
+        assert_eq!(book.buy_volume, dec!(2));
+        assert_eq!(book.get_order(buy).unwrap().quantity(), dec!(2));
+
+        // book is never left crossed
+        let best_bid = *book.buy_orders.keys().next_back().unwrap();
+        let best_ask = *book.sell_orders.keys().next().unwrap();
+        assert!(best_bid < best_ask);
+    }
+
+    #[test]
+    pub fn pass_match_limit_sell_against_best_bid_first() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let low_bid = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(98)), dec!(5))
+            .expect("can't add limit buy")
+            .order_id;
+        let high_bid = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(5))
+            .expect("can't add limit buy")
+            .order_id;
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(99)), dec!(5))
+            .expect("can't add limit sell");
+
+        assert!(book.get_order(high_bid).is_none());
+        assert_eq!(book.get_order(low_bid).unwrap().quantity(), dec!(5));
+        assert_eq!(book.buy_volume, dec!(5));
+        assert_eq!(book.sell_volume, dec!(0));
+        assert!(book.sell_orders.is_empty());
+    }
+
+    #[test]
+    pub fn pass_market_sell_sweeps_best_bid_first() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let low_bid = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(98)), dec!(5))
+            .expect("can't add limit buy")
+            .order_id;
+        let high_bid = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(5))
+            .expect("can't add limit buy")
+            .order_id;
+
+        book.add_order(Side::Sell, OrderKind::Market, None, dec!(7))
+            .expect("can't add market sell");
+
+        assert!(book.get_order(high_bid).is_none());
+        assert_eq!(book.get_order(low_bid).unwrap().quantity(), dec!(3));
+        assert_eq!(book.buy_volume, dec!(3));
+        // the emptied 101 level is pruned
+        assert_eq!(book.buy_orders.len(), 1);
+    }
+
+    #[test]
+    pub fn pass_market_order_remainder_cancelled() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(103)), dec!(2))
+            .expect("can't add limit sell");
+
+        let buy = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(10))
+            .expect("can't add market buy")
+            .order_id;
+
+        let order = book.get_order(buy.clone()).unwrap();
+        assert_eq!(*order.status(), OrderStatus::Cancelled);
+        assert_eq!(order.quantity(), dec!(6));
+        assert!(book.sell_orders.is_empty());
+        assert_eq!(book.sell_volume, dec!(0));
+        assert_eq!(book.buy_volume, dec!(0));
+
+        assert!(book.cancel_order(buy).is_err());
+    }
+
+    #[test]
+    pub fn pass_execution_report_lists_fills() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let first_maker = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell")
+            .order_id;
+        let second_maker = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(102)), dec!(2))
+            .expect("can't add limit sell")
+            .order_id;
+
+        let report = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(102)), dec!(5))
+            .expect("can't add limit buy");
+
+        assert_eq!(report.status, OrderStatus::PartiallyExecuted);
+        assert_eq!(report.filled_quantity, dec!(4));
+        assert_eq!(report.remaining_quantity, dec!(1));
+        assert_eq!(report.average_price, Some(dec!(101)));
+
+        assert_eq!(report.fills.len(), 2);
+        assert_eq!(report.fills[0].maker_order_id, first_maker);
+        assert_eq!(report.fills[0].price, dec!(100));
+        assert_eq!(report.fills[1].maker_order_id, second_maker);
+        assert_eq!(report.fills[1].price, dec!(102));
+        assert!(
+            report
+                .fills
+                .iter()
+                .all(|fill| fill.taker_order_id == report.order_id
+                    && fill.aggressor_side == Side::Buy)
+        );
+        assert_ne!(report.fills[0].trade_id, report.fills[1].trade_id);
+    }
+
+    #[test]
+    pub fn pass_ticker_tracks_last_trade() {
+        let listing_price = dec!(100);
+        let pair_id = String::from("ETHINC");
+
+        let mut book = OrderBook::new(pair_id, listing_price, 8);
+
+        let ticker = book.ticker();
+        assert_eq!(ticker.last_price, dec!(100));
+        assert_eq!(ticker.volume_24h, dec!(0));
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(2))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(104)), dec!(2))
+            .expect("can't add limit sell");
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(95)), dec!(2))
+            .expect("can't add limit buy");
+        book.add_order(Side::Buy, OrderKind::Market, None, dec!(3))
+            .expect("can't add market buy");
+
+        assert_eq!(book.last_traded_price(), dec!(104));
+
+        let ticker = book.ticker();
+        assert_eq!(ticker.last_price, dec!(104));
+        assert_eq!(ticker.last_size, dec!(1));
+        assert_eq!(ticker.best_bid, Some(dec!(95)));
+        assert_eq!(ticker.best_ask, Some(dec!(104)));
+        assert_eq!(ticker.high_24h, dec!(104));
+        assert_eq!(ticker.low_24h, dec!(101));
+        assert_eq!(ticker.volume_24h, dec!(3));
+        assert_eq!(ticker.change_24h, dec!(4));
+    }
+
+    #[test]
+    pub fn pass_fractional_fills_leave_no_dust() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(0.3))
+            .expect("can't add limit sell")
+            .order_id;
+
+        book.add_order(Side::Buy, OrderKind::Market, None, dec!(0.1))
+            .expect("can't add market buy");
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(0.2))
+            .expect("can't add market buy");
+
+        assert_eq!(report.status, OrderStatus::Executed);
+        assert!(book.get_order(sell).is_none());
+        assert_eq!(book.sell_volume, Decimal::ZERO);
+        assert!(book.sell_orders.is_empty());
+    }
+
+    #[test]
+    pub fn fail_quantity_beyond_pair_scale() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 2);
+
+        assert!(book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(0.001))
+            .is_err());
+        assert!(book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(0.010))
+            .is_ok());
+        assert!(book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), Decimal::ZERO)
+            .is_err());
+    }
+
+    fn with_tif(time_in_force: TimeInForce) -> OrderOptions {
+        OrderOptions {
+            time_in_force,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn pass_ioc_cancels_remainder() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell");
+
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(5),
+                with_tif(TimeInForce::Ioc),
+            )
+            .expect("can't add ioc buy");
+
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert_eq!(report.filled_quantity, dec!(2));
+        assert_eq!(report.remaining_quantity, dec!(3));
+        assert!(book.buy_orders.is_empty());
+        assert_eq!(book.buy_volume, Decimal::ZERO);
+    }
+
+    #[test]
+    pub fn pass_fok_kills_without_touching_book() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(105)), dec!(2))
+            .expect("can't add limit sell");
+
+        // only 2 are available at or below 101
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(3),
+                with_tif(TimeInForce::Fok),
+            )
+            .expect("can't add fok buy");
+
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert!(report.fills.is_empty());
+        assert_eq!(book.sell_volume, dec!(4));
+        assert_eq!(book.last_traded_price(), dec!(100));
+
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(105)),
+                dec!(3),
+                with_tif(TimeInForce::Fok),
+            )
+            .expect("can't add fok buy");
+
+        assert_eq!(report.status, OrderStatus::Executed);
+        assert_eq!(report.filled_quantity, dec!(3));
+        assert_eq!(book.sell_volume, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_gtd_orders_expire() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let expiry = Utc::now().timestamp() + 60;
+        let order_id = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(99)),
+                dec!(2),
+                with_tif(TimeInForce::Gtd(expiry)),
+            )
+            .expect("can't add gtd buy")
+            .order_id;
+
+        assert!(book.expire_orders(expiry - 1).is_empty());
+        assert_eq!(book.expire_orders(expiry), vec![order_id.clone()]);
+
+        let order = book.get_order(order_id.clone()).unwrap();
+        assert_eq!(*order.status(), OrderStatus::Expired);
+        assert_eq!(book.buy_volume, Decimal::ZERO);
+        assert!(book.buy_orders.is_empty());
+        assert!(book.cancel_order(order_id).is_err());
+    }
+
+    #[test]
+    pub fn pass_day_orders_purged_at_session_end() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let day_order = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(2),
+                with_tif(TimeInForce::Day),
+            )
+            .expect("can't add day sell")
+            .order_id;
+        let gtc_order = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(3))
+            .expect("can't add gtc sell")
+            .order_id;
+
+        assert_eq!(book.end_session(), vec![day_order.clone()]);
+        assert_eq!(
+            *book.get_order(day_order).unwrap().status(),
+            OrderStatus::Expired
+        );
+        assert_eq!(
+            *book.get_order(gtc_order).unwrap().status(),
+            OrderStatus::Open
+        );
+        assert_eq!(book.sell_volume, dec!(3));
+    }
+
+    fn post_only(post_only: PostOnly) -> OrderOptions {
+        OrderOptions {
+            post_only: Some(post_only),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn pass_post_only_rejected_when_crossing() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell");
+
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(1),
+                post_only(PostOnly::Reject),
+            )
+            .expect("can't add post-only buy");
+
+        assert_eq!(report.status, OrderStatus::Rejected);
+        assert_eq!(report.reject_reason, Some(RejectReason::PostOnlyWouldCross));
+        assert!(report.fills.is_empty());
+        assert!(book.get_order(report.order_id).is_none());
+        assert_eq!(book.sell_volume, dec!(2));
+        assert!(book.buy_orders.is_empty());
+
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(99.5)),
+                dec!(1),
+                post_only(PostOnly::Reject),
+            )
+            .expect("can't add post-only buy");
+
+        assert_eq!(report.status, OrderStatus::Open);
+        assert_eq!(report.reject_reason, None);
+        assert_eq!(book.buy_volume, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_post_only_repriced_behind_best() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_rules(InstrumentRules {
+            tick_size: Some(dec!(0.5)),
+            ..Default::default()
+        });
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit buy");
+
+        let report = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(98)),
+                dec!(1),
+                post_only(PostOnly::Reprice),
+            )
+            .expect("can't add post-only sell");
+
+        assert_eq!(report.status, OrderStatus::Open);
+        assert_eq!(report.price, Some(dec!(100.5)));
+        assert!(report.fills.is_empty());
+        assert_eq!(book.best_ask(), Some(dec!(100.5)));
+        assert_eq!(book.buy_volume, dec!(2));
+    }
+
+    #[test]
+    pub fn fail_post_only_market_order() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        assert_eq!(
+            book.add_order_with_options(
+                Side::Buy,
+                OrderKind::Market,
+                None,
+                dec!(1),
+                post_only(PostOnly::Reject),
+            ),
+            Err(MatcherError::PostOnlyRequiresLimit)
+        );
+    }
+
+    #[test]
+    pub fn pass_stop_orders_cascade_within_one_call() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(99)), dec!(1))
+            .expect("can't add limit buy");
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(98)), dec!(5))
+            .expect("can't add limit buy");
+
+        let deep_stop = book
+            .add_order(
+                Side::Sell,
+                OrderKind::Stop {
+                    stop_price: dec!(98.5),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add sell stop");
+        let near_stop = book
+            .add_order(
+                Side::Sell,
+                OrderKind::Stop {
+                    stop_price: dec!(99.5),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add sell stop");
+
+        assert_eq!(near_stop.status, OrderStatus::Open);
+        assert!(near_stop.fills.is_empty());
+        assert_eq!(book.buy_volume, dec!(6));
+
+        let report = book
+            .add_order(Side::Sell, OrderKind::Market, None, dec!(1))
+            .expect("can't add market sell");
+
+        // 99 trades, triggering the 99.5 stop, whose fill at 98 triggers the 98.5 stop
+        assert_eq!(report.fills[0].price, dec!(99));
+        assert_eq!(report.triggered.len(), 2);
+        assert_eq!(report.triggered[0].order_id, near_stop.order_id);
+        assert_eq!(report.triggered[0].status, OrderStatus::Executed);
+        assert_eq!(report.triggered[0].fills[0].price, dec!(98));
+        assert_eq!(report.triggered[1].order_id, deep_stop.order_id);
+        assert_eq!(report.triggered[1].status, OrderStatus::Executed);
+
+        assert_eq!(book.last_traded_price(), dec!(98));
+        assert_eq!(book.buy_volume, dec!(3));
+        assert!(book.sell_stops.is_empty());
+    }
+
+    #[test]
+    pub fn pass_stop_limit_rests_after_trigger() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(103)), dec!(1))
+            .expect("can't add limit sell");
+
+        let stop = book
+            .add_order(
+                Side::Buy,
+                OrderKind::StopLimit {
+                    stop_price: dec!(101),
+                },
+                Some(dec!(102)),
+                dec!(2),
+            )
+            .expect("can't add buy stop limit")
+            .order_id;
+
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+            .expect("can't add market buy");
+
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(report.triggered[0].order_id, stop);
+        assert!(report.triggered[0].fills.is_empty());
+
+        let order = book.get_order(stop).unwrap();
+        assert_eq!(*order.kind(), OrderKind::Limit);
+        assert_eq!(book.best_bid(), Some(dec!(102)));
+        assert_eq!(book.buy_volume, dec!(2));
+    }
+
+    #[test]
+    pub fn pass_cancel_parked_stop() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let stop = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Stop {
+                    stop_price: dec!(105),
+                },
+                None,
+                dec!(2),
+            )
+            .expect("can't add buy stop")
+            .order_id;
+
+        assert_eq!(book.buy_volume, Decimal::ZERO);
+        assert!(book
+            .update_order(stop.clone(), Some(dec!(1)), None, None)
+            .is_err());
+
+        book.cancel_order(stop.clone()).expect("can't cancel stop");
+        assert!(book.buy_stops.is_empty());
+        assert!(book.get_order(stop).is_none());
+    }
+
+    #[test]
+    pub fn pass_trailing_stop_ratchets_and_triggers() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let stop = book
+            .add_order(
+                Side::Sell,
+                OrderKind::TrailingStop {
+                    offset: TrailingOffset::Amount(dec!(2)),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add trailing stop")
+            .order_id;
+
+        let trigger_price = |book: &OrderBook| {
+            book.get_order(stop.clone())
+                .unwrap()
+                .trigger_price()
+                .unwrap()
+        };
+        assert_eq!(trigger_price(&book), dec!(98));
+
+        // the market rallies to 105, the trigger follows
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(105)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+            .expect("can't add market buy");
+        assert_eq!(trigger_price(&book), dec!(103));
+
+        // a dip to 104 does not move it back down
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(104)), dec!(1))
+            .expect("can't add limit buy");
+        book.add_order(Side::Sell, OrderKind::Market, None, dec!(1))
+            .expect("can't add market sell");
+        assert_eq!(trigger_price(&book), dec!(103));
+
+        // trading at 103 triggers it as a market sell into the 101 bid
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(103)), dec!(1))
+            .expect("can't add limit buy");
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit buy");
+        let report = book
+            .add_order(Side::Sell, OrderKind::Market, None, dec!(1))
+            .expect("can't add market sell");
+
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(report.triggered[0].order_id, stop);
+        assert_eq!(report.triggered[0].fills[0].price, dec!(101));
+        assert!(book.sell_stops.is_empty());
+    }
+
+    #[test]
+    pub fn pass_trailing_stop_limit_in_basis_points() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let stop = book
+            .add_order(
+                Side::Buy,
+                OrderKind::TrailingStopLimit {
+                    offset: TrailingOffset::BasisPoints(100),
+                    limit_offset: dec!(0.5),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add trailing stop limit")
+            .order_id;
+
+        assert_eq!(
+            book.get_order(stop.clone()).unwrap().trigger_price(),
+            Some(dec!(101))
+        );
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+            .expect("can't add market buy");
+
+        // triggered at 101, rests as a limit buy at 101.5 with nothing left to hit
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(report.triggered[0].price, Some(dec!(101.5)));
+        assert_eq!(book.best_bid(), Some(dec!(101.5)));
+    }
+
+    #[test]
+    pub fn pass_cancel_triggered_trailing_stop_limit() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let stop = book
+            .add_order(
+                Side::Sell,
+                OrderKind::TrailingStopLimit {
+                    offset: TrailingOffset::Amount(dec!(5)),
+                    limit_offset: dec!(1),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add trailing stop limit")
+            .order_id;
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(95)), dec!(1))
+            .expect("can't add limit buy");
+        let report = book
+            .add_order(Side::Sell, OrderKind::Market, None, dec!(1))
+            .expect("can't add market sell");
+
+        // triggered at 95, rests as a limit sell at 94
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(book.best_ask(), Some(dec!(94)));
+        assert_eq!(book.get_order(stop.clone()).unwrap().trigger_price(), None);
+
+        book.cancel_order(stop)
+            .expect("can't cancel triggered stop");
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.sell_volume, dec!(0));
+    }
+
+    #[test]
+    pub fn pass_computed_prices_rounded_to_tick() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_rules(InstrumentRules {
+            tick_size: Some(dec!(0.01)),
+            ..Default::default()
+        });
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(99.99)), dec!(1))
+            .expect("can't add limit buy");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+
+        let mut midpoint = |side| {
+            book.add_order(
+                side,
+                OrderKind::Pegged {
+                    reference: PegReference::Midpoint,
+                    offset: dec!(0),
+                    cap: None,
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add midpoint peg")
+            .price
+        };
+        assert_eq!(midpoint(Side::Buy), Some(dec!(99.99)));
+        assert_eq!(midpoint(Side::Sell), Some(dec!(100)));
+
+        let mut book = OrderBook::new(String::from("ETHINC"), dec!(100), 8);
+        book.set_rules(InstrumentRules {
+            tick_size: Some(dec!(0.5)),
+            ..Default::default()
+        });
+        book.add_order(
+            Side::Buy,
+            OrderKind::TrailingStopLimit {
+                offset: TrailingOffset::BasisPoints(150),
+                limit_offset: dec!(0.25),
+            },
+            None,
+            dec!(1),
+        )
+        .expect("can't add trailing stop limit");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101.5)), dec!(1))
+            .expect("can't add limit sell");
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+            .expect("can't add market buy");
+
+        // triggered at 101.5, the 101.75 limit rounds down onto the tick
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(report.triggered[0].price, Some(dec!(101.5)));
+    }
+
+    fn iceberg(display_quantity: Decimal) -> OrderOptions {
+        OrderOptions {
+            display_quantity: Some(display_quantity),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn pass_iceberg_shows_only_display_quantity() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order_with_options(
+            Side::Sell,
+            OrderKind::Limit,
+            Some(dec!(101)),
+            dec!(10),
+            iceberg(dec!(2)),
+        )
+        .expect("can't add iceberg sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+
+        let depth = book.depth(5);
+        assert_eq!(depth.asks.len(), 1);
+        assert_eq!(depth.asks[0].quantity, dec!(3));
+        assert!(depth.bids.is_empty());
+        assert_eq!(book.sell_volume, dec!(11));
+    }
+
+    #[test]
+    pub fn pass_iceberg_replenishes_behind_the_queue() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let iceberg_id = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(5),
+                iceberg(dec!(2)),
+            )
+            .expect("can't add iceberg sell")
+            .order_id;
+        let plain_id = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell")
+            .order_id;
+
+        // takes the 2 slice, then the plain order now ahead, then 1 from the refreshed slice
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(4))
+            .expect("can't add market buy");
+
+        let makers: Vec<(&String, Decimal)> = report
+            .fills
+            .iter()
+            .map(|fill| (&fill.maker_order_id, fill.quantity))
+            .collect();
+        assert_eq!(
+            makers,
+            vec![
+                (&iceberg_id, dec!(2)),
+                (&plain_id, dec!(1)),
+                (&iceberg_id, dec!(1))
+            ]
+        );
+
+        let order = book.get_order(iceberg_id).unwrap();
+        assert_eq!(order.quantity(), dec!(2));
+        assert_eq!(order.visible_quantity(), dec!(1));
+        assert_eq!(book.sell_volume, dec!(2));
+        assert_eq!(book.depth(1).asks[0].quantity, dec!(1));
+    }
+
+    #[test]
+    pub fn fail_iceberg_market_order() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        assert_eq!(
+            book.add_order_with_options(
+                Side::Buy,
+                OrderKind::Market,
+                None,
+                dec!(5),
+                iceberg(dec!(1)),
+            ),
+            Err(MatcherError::InvalidDisplayQuantity)
+        );
+    }
+
+    fn hidden() -> OrderOptions {
+        OrderOptions {
+            hidden: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn pass_hidden_orders_never_displayed() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order_with_options(
+            Side::Buy,
+            OrderKind::Limit,
+            Some(dec!(100)),
+            dec!(5),
+            hidden(),
+        )
+        .expect("can't add hidden buy");
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(99)), dec!(1))
+            .expect("can't add limit buy");
+
+        let depth = book.depth(5);
+        assert_eq!(depth.bids.len(), 1);
+        assert_eq!(depth.bids[0].price, dec!(99));
+        assert_eq!(book.best_bid(), Some(dec!(99)));
+        assert_eq!(book.ticker().best_bid, Some(dec!(99)));
+        assert_eq!(book.buy_volume, dec!(6));
+
+        // still matches, at the better hidden price first
+        let report = book
+            .add_order(Side::Sell, OrderKind::Market, None, dec!(2))
+            .expect("can't add market sell");
+        assert_eq!(report.fills[0].price, dec!(100));
+        assert_eq!(report.filled_quantity, dec!(2));
+
+        assert_eq!(
+            book.add_order_with_options(Side::Buy, OrderKind::Market, None, dec!(1), hidden())
+                .unwrap_err(),
+            MatcherError::HiddenRequiresLimit
+        );
+
+        // a post-only order isn't turned away by liquidity it can't see
+        let report = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(1),
+                post_only(PostOnly::Reject),
+            )
+            .expect("can't add post-only sell");
+        assert_eq!(report.reject_reason, None);
+        assert_eq!(report.filled_quantity, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_hidden_ranks_behind_displayed() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let hidden_id = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(2),
+                hidden(),
+            )
+            .expect("can't add hidden sell")
+            .order_id;
+        let displayed_id = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(2))
+            .expect("can't add limit sell")
+            .order_id;
+
+        // post-only still sees the hidden liquidity it would cross
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(1),
+                post_only(PostOnly::Reject),
+            )
+            .expect("can't add post-only buy");
+        assert_eq!(report.reject_reason, Some(RejectReason::PostOnlyWouldCross));
+
+        let report = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(3))
+            .expect("can't add limit buy");
+
+        assert_eq!(report.fills[0].maker_order_id, displayed_id);
+        assert_eq!(report.fills[0].quantity, dec!(2));
+        assert_eq!(report.fills[1].maker_order_id, hidden_id);
+        assert_eq!(report.fills[1].quantity, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_pegged_orders_follow_top_of_book() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let peg = |reference| OrderKind::Pegged {
+            reference,
+            offset: dec!(0.5),
+            cap: None,
+        };
+
+        assert_eq!(
+            book.add_order(Side::Buy, peg(PegReference::Midpoint), None, dec!(1))
+                .unwrap_err(),
+            MatcherError::PegUnavailable
+        );
+
+        let bid_id = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(99)), dec!(1))
+            .expect("can't add limit buy")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(103)), dec!(1))
+            .expect("can't add limit sell");
+
+        let primary = book
+            .add_order(Side::Buy, peg(PegReference::Primary), None, dec!(1))
+            .expect("can't add primary peg");
+        assert_eq!(primary.price, Some(dec!(98.5)));
+        let midpoint = book
+            .add_order(Side::Buy, peg(PegReference::Midpoint), None, dec!(1))
+            .expect("can't add midpoint peg");
+        assert_eq!(midpoint.price, Some(dec!(100.5)));
+
+        // a better bid moves both pegs up
+        let better_bid = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit buy")
+            .order_id;
+        let amends = book.drain_peg_amends();
+        assert_eq!(amends.len(), 2);
+        assert_eq!(amends[0].order_id, primary.order_id);
+        assert_eq!(amends[0].old_price, dec!(98.5));
+        assert_eq!(amends[0].new_price, dec!(99.5));
+        assert_eq!(amends[1].new_price, dec!(101));
+
+        // and falls back once it is cancelled
+        book.cancel_order(better_bid).expect("can't cancel bid");
+        let amends = book.drain_peg_amends();
+        assert_eq!(amends.len(), 2);
+        assert_eq!(
+            book.get_order(primary.order_id.clone()).unwrap().price(),
+            &Some(dec!(98.5))
+        );
+
+        // with no bid left the primary peg keeps its last price
+        book.cancel_order(bid_id).expect("can't cancel bid");
+        assert!(book.drain_peg_amends().is_empty());
+        assert_eq!(
+            book.get_order(primary.order_id).unwrap().price(),
+            &Some(dec!(98.5))
+        );
+    }
+
+    #[test]
+    pub fn pass_pegged_order_matches_when_marketable() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(103)), dec!(1))
+            .expect("can't add limit sell");
+
+        let report = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Pegged {
+                    reference: PegReference::Market,
+                    offset: Decimal::ZERO,
+                    cap: Some(dec!(102)),
+                },
+                None,
+                dec!(3),
+            )
+            .expect("can't add market peg");
+        assert_eq!(report.fills.len(), 1);
+        assert_eq!(report.fills[0].price, dec!(100));
+
+        // the peg follows the ask up, trading at 101 and then resting at its cap
+        let amends = book.drain_peg_amends();
+        assert_eq!(amends.len(), 2);
+        assert_eq!(amends[0].new_price, dec!(101));
+        assert_eq!(amends[0].report.fills[0].price, dec!(101));
+        assert_eq!(amends[1].new_price, dec!(102));
+        assert!(amends[1].report.fills.is_empty());
+
+        let depth = book.depth(5);
+        assert_eq!(depth.bids[0].price, dec!(102));
+        assert_eq!(depth.bids[0].quantity, dec!(1));
+        assert_eq!(depth.asks[0].price, dec!(103));
+    }
+
+    fn take_profit_and_stop() -> (OrderRequest, OrderRequest) {
+        let take_profit = OrderRequest {
+            side: Side::Sell,
+            kind: OrderKind::Limit,
+            price: Some(dec!(110)),
+            quantity: dec!(2),
+            options: OrderOptions::default(),
+        };
+        let stop = OrderRequest {
+            side: Side::Sell,
+            kind: OrderKind::Stop {
+                stop_price: dec!(95),
+            },
+            price: None,
+            quantity: dec!(2),
+            options: OrderOptions::default(),
+        };
+        (take_profit, stop)
+    }
+
+    #[test]
+    pub fn pass_oco_fills_reduce_then_cancel_sibling() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let (take_profit, stop) = take_profit_and_stop();
+        let oco = book
+            .add_oco_orders(take_profit, stop)
+            .expect("can't add oco orders");
+        let stop_id = oco.second.order_id;
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(110)), dec!(1))
+            .expect("can't add limit buy");
+        let stop = book.get_order(stop_id.clone()).unwrap();
+        assert_eq!(stop.quantity(), dec!(1));
+        assert_eq!(*stop.status(), OrderStatus::Open);
+
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(110)), dec!(1))
+            .expect("can't add limit buy");
+        assert_eq!(
+            *book.get_order(stop_id.clone()).unwrap().status(),
+            OrderStatus::Cancelled
+        );
+
+        // the cancelled stop no longer triggers
+        book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(90)), dec!(1))
+            .expect("can't add limit buy");
+        let report = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(90)), dec!(1))
+            .expect("can't add limit sell");
+        assert!(report.triggered.is_empty());
+        assert_eq!(
+            book.cancel_oco_group(oco.group_id.clone()),
+            Err(MatcherError::UnknownGroup(oco.group_id))
+        );
+    }
+
+    #[test]
+    pub fn fail_oco_legs_that_cross() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let leg = |side, kind, price| OrderRequest {
+            side,
+            kind,
+            price,
+            quantity: dec!(1),
+            options: OrderOptions::default(),
+        };
+
+        assert_eq!(
+            book.add_oco_orders(
+                leg(Side::Buy, OrderKind::Limit, Some(dec!(100))),
+                leg(Side::Sell, OrderKind::Limit, Some(dec!(99))),
+            ),
+            Err(MatcherError::OcoLegsCross)
+        );
+        assert_eq!(
+            book.add_oco_orders(
+                leg(Side::Sell, OrderKind::Market, None),
+                leg(Side::Buy, OrderKind::Limit, Some(dec!(99))),
+            ),
+            Err(MatcherError::OcoLegsCross)
+        );
+        assert_eq!(book.buy_volume, dec!(0));
+        assert_eq!(book.sell_volume, dec!(0));
+
+        book.add_oco_orders(
+            leg(Side::Buy, OrderKind::Limit, Some(dec!(99))),
+            leg(Side::Sell, OrderKind::Limit, Some(dec!(101))),
+        )
+        .expect("can't add oco orders");
+        assert_eq!(book.buy_volume, dec!(1));
+        assert_eq!(book.sell_volume, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_cancel_oco_group() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let (take_profit, stop) = take_profit_and_stop();
+        let oco = book
+            .add_oco_orders(take_profit, stop)
+            .expect("can't add oco orders");
+        assert_eq!(book.sell_volume, dec!(2));
+
+        book.cancel_oco_group(oco.group_id)
+            .expect("can't cancel oco group");
+        assert_eq!(book.sell_volume, Decimal::ZERO);
+        assert!(book.get_order(oco.first.order_id).is_none());
+        assert!(book.get_order(oco.second.order_id.clone()).is_none());
+        assert_eq!(
+            book.cancel_order(oco.second.order_id.clone()),
+            Err(MatcherError::UnknownOrder(oco.second.order_id))
+        );
+    }
+
+    fn owned(owner: &str, self_trade_prevention: SelfTradePrevention) -> OrderOptions {
+        OrderOptions {
+            owner: Some(String::from(owner)),
+            self_trade_prevention,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn pass_self_trade_prevention() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let own_sell = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(3),
+                owned("firm-a", SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add own sell")
+            .order_id;
+        let other_sell = book
+            .add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(1),
+                owned("firm-b", SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add other sell")
+            .order_id;
+
+        // cancel newest leaves the resting order alone
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(1),
+                owned("firm-a", SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add own buy");
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert!(report.fills.is_empty());
+        assert_eq!(book.sell_volume, dec!(4));
+
+        // decrement and cancel takes the smaller quantity off both
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Market,
+                None,
+                dec!(1),
+                owned("firm-a", SelfTradePrevention::DecrementAndCancel),
+            )
+            .expect("can't add own buy");
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert!(report.fills.is_empty());
+        assert_eq!(
+            book.get_order(own_sell.clone()).unwrap().quantity(),
+            dec!(2)
+        );
+        assert_eq!(book.sell_volume, dec!(3));
+        let history = book.order_history(own_sell.clone()).unwrap();
+        assert_eq!(history.last().unwrap().kind, OrderEventKind::Amended);
+        assert_eq!(history.last().unwrap().quantity, dec!(2));
+
+        // cancel oldest clears the own order and trades with the next one
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(100)),
+                dec!(1),
+                owned("firm-a", SelfTradePrevention::CancelOldest),
+            )
+            .expect("can't add own buy");
+        assert_eq!(report.status, OrderStatus::Executed);
+        assert_eq!(report.fills[0].maker_order_id, other_sell);
+        assert_eq!(
+            *book.get_order(own_sell).unwrap().status(),
+            OrderStatus::Cancelled
+        );
+        assert_eq!(book.sell_volume, Decimal::ZERO);
+    }
+
+    #[test]
+    pub fn pass_fok_counts_only_tradeable_liquidity() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        for (price, owner) in [
+            (dec!(100), "firm-b"),
+            (dec!(100), "firm-a"),
+            (dec!(101), "firm-b"),
+        ] {
+            book.add_order_with_options(
+                Side::Sell,
+                OrderKind::Limit,
+                Some(price),
+                dec!(1),
+                owned(owner, SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add limit sell");
+        }
+        let fok = |self_trade_prevention| OrderOptions {
+            time_in_force: TimeInForce::Fok,
+            ..owned("firm-a", self_trade_prevention)
+        };
+
+        // its own order would cancel it halfway, so it never starts
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(2),
+                fok(SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add fok buy");
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert_eq!(report.filled_quantity, dec!(0));
+        assert_eq!(book.sell_volume, dec!(3));
+
+        // cancelling the resting order clears the way
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(101)),
+                dec!(2),
+                fok(SelfTradePrevention::CancelOldest),
+            )
+            .expect("can't add fok buy");
+        assert_eq!(report.status, OrderStatus::Executed);
+        assert_eq!(report.filled_quantity, dec!(2));
+        assert_eq!(book.sell_volume, dec!(0));
+    }
+
+    #[test]
+    pub fn pass_pro_rata_matching() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 0);
+        book.set_allocation_policy(AllocationPolicy::ProRata {
+            min_allocation: Decimal::ZERO,
+        });
+
+        let small = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell")
+            .order_id;
+        let large = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(6))
+            .expect("can't add limit sell")
+            .order_id;
+
+        // FIFO would fill the first order only
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(2))
+            .expect("can't add market buy");
+        assert_eq!(report.fills.len(), 2);
+        assert_eq!(report.fills[0].maker_order_id, small);
+        assert_eq!(report.fills[0].quantity, dec!(1));
+        assert_eq!(report.fills[1].maker_order_id, large);
+        assert_eq!(report.fills[1].quantity, dec!(1));
+        assert_eq!(book.sell_volume, dec!(6));
+    }
+
+    #[test]
+    pub fn pass_uncross_prevents_self_trades() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.start_auction();
+
+        let mut add = |side, price, owner| {
+            book.add_order_with_options(
+                side,
+                OrderKind::Limit,
+                Some(price),
+                dec!(1),
+                owned(owner, SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add limit order")
+            .order_id
+        };
+        let buy = add(Side::Buy, dec!(101), "firm-a");
+        let own_sell = add(Side::Sell, dec!(99), "firm-a");
+        let other_sell = add(Side::Sell, dec!(100), "firm-b");
+
+        // the newer own sell goes, the buy trades with the other firm instead
+        let uncross = book.uncross();
+        assert_eq!(uncross.fills.len(), 1);
+        assert_eq!(uncross.fills[0].taker_order_id, buy);
+        assert_eq!(uncross.fills[0].maker_order_id, other_sell);
+        assert_eq!(
+            *book.get_order(own_sell).unwrap().status(),
+            OrderStatus::Cancelled
+        );
+        assert_eq!(book.sell_volume, Decimal::ZERO);
+    }
+
+    #[test]
+    pub fn pass_call_auction() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.start_auction();
+
+        let buy = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(2))
+            .expect("can't add limit buy")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(99)), dec!(1))
+            .expect("can't add limit sell");
+        let report = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(2))
+            .expect("can't add limit sell");
+        assert!(report.fills.is_empty());
+        assert_eq!(
+            book.add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+                .unwrap_err(),
+            MatcherError::NotAllowedInAuction
+        );
+        assert_eq!(
+            book.update_order(buy.clone(), None, Some(OrderKind::Market), None),
+            Err(MatcherError::NotAllowedInAuction)
+        );
+        assert_eq!(book.buy_volume, dec!(2));
+
+        // 100 and 101 both execute 2 with the same imbalance, 100 is the reference
+        let indicative = book.indicative_uncross().unwrap();
+        assert_eq!(indicative.price, dec!(100));
+        assert_eq!(indicative.volume, dec!(2));
+        assert_eq!(indicative.imbalance, dec!(-1));
+
+        let uncross = book.uncross();
+        assert_eq!(book.phase(), TradingPhase::Continuous);
+        assert_eq!(uncross.price, Some(dec!(100)));
+        assert_eq!(uncross.volume, dec!(2));
+        assert_eq!(uncross.fills.len(), 2);
+        assert!(uncross
+            .fills
+            .iter()
+            .all(|fill| fill.price == dec!(100) && fill.taker_order_id == buy));
+
+        assert!(book.get_order(buy).is_none());
+        assert_eq!(book.buy_volume, Decimal::ZERO);
+        assert_eq!(book.sell_volume, dec!(1));
+        assert_eq!(book.best_ask(), Some(dec!(100)));
+        assert_eq!(book.last_traded_price(), dec!(100));
+    }
+
+    #[test]
+    pub fn pass_price_band_breach() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_price_band(Some(PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::VolatilityAuction,
+            duration: 60,
+        }));
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(110)), dec!(1))
+            .expect("can't add limit sell");
+
+        // 110 is more than 5% away from the 101 print, the sweep stops short of it
+        let report = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(110)), dec!(2))
+            .expect("can't add limit buy");
+        assert_eq!(report.filled_quantity, dec!(1));
+        assert_eq!(report.status, OrderStatus::PartiallyExecuted);
+        assert_eq!(book.phase(), TradingPhase::Auction);
+        assert_eq!(book.last_traded_price(), dec!(101));
+
+        let now = Utc::now().timestamp();
+        assert!(book.resume_trading(now).is_none());
+
+        let uncross = book.resume_trading(now + 61).expect("book didn't reopen");
+        assert_eq!(uncross.price, Some(dec!(110)));
+        assert_eq!(uncross.volume, dec!(1));
+        assert_eq!(book.phase(), TradingPhase::Continuous);
+    }
+
+    #[test]
+    pub fn pass_fok_never_trips_price_band() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_price_band(Some(PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::VolatilityAuction,
+            duration: 60,
+        }));
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(110)), dec!(1))
+            .expect("can't add limit sell");
+
+        // the second level lies outside the band, so nothing trades
+        let report = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(110)),
+                dec!(2),
+                with_tif(TimeInForce::Fok),
+            )
+            .expect("can't add fok buy");
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        assert_eq!(report.filled_quantity, dec!(0));
+        assert_eq!(book.phase(), TradingPhase::Continuous);
+        assert_eq!(book.sell_volume, dec!(2));
+    }
+
+    #[test]
+    pub fn pass_stops_wait_for_reopening() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_price_band(Some(PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::VolatilityAuction,
+            duration: 60,
+        }));
+
+        let stop = book
+            .add_order(
+                Side::Buy,
+                OrderKind::Stop {
+                    stop_price: dec!(101),
+                },
+                None,
+                dec!(1),
+            )
+            .expect("can't add stop buy")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(110)), dec!(1))
+            .expect("can't add limit sell");
+
+        // the 101 print reaches the stop but the breach that follows holds it back
+        let report = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(110)), dec!(2))
+            .expect("can't add limit buy");
+        assert!(report.triggered.is_empty());
+        assert_eq!(book.phase(), TradingPhase::Auction);
+        assert_eq!(
+            *book.get_order(stop.clone()).unwrap().status(),
+            OrderStatus::Open
+        );
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(111)), dec!(1))
+            .expect("can't add limit sell");
+        let uncross = book
+            .resume_trading(Utc::now().timestamp() + 61)
+            .expect("book didn't reopen");
+        assert_eq!(uncross.price, Some(dec!(110)));
+        assert_eq!(uncross.triggered.len(), 1);
+        assert_eq!(uncross.triggered[0].order_id, stop);
+        assert_eq!(uncross.triggered[0].filled_quantity, dec!(1));
+    }
+
+    #[test]
+    pub fn pass_stop_cascade_stops_at_breach() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_price_band(Some(PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::VolatilityAuction,
+            duration: 60,
+        }));
+
+        let stop = OrderKind::Stop {
+            stop_price: dec!(101),
+        };
+        let first = book
+            .add_order(Side::Buy, stop, None, dec!(1))
+            .expect("can't add stop buy")
+            .order_id;
+        let second = book
+            .add_order(Side::Buy, stop, None, dec!(1))
+            .expect("can't add stop buy")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(120)), dec!(1))
+            .expect("can't add limit sell");
+
+        // the 101 print fires the first stop, which runs into 120 and trips the band
+        let report = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit buy");
+        assert_eq!(report.triggered.len(), 1);
+        assert_eq!(report.triggered[0].order_id, first);
+        assert_eq!(book.phase(), TradingPhase::Auction);
+
+        let second = book.get_order(second).unwrap();
+        assert_eq!(*second.status(), OrderStatus::Open);
+        assert!(second.kind().is_stop());
+    }
+
+    #[test]
+    pub fn pass_price_band_halt() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_price_band(Some(PriceBand {
+            width_bps: 500,
+            on_breach: BreachAction::Halt,
+            duration: 60,
+        }));
+
+        let sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(90)), dec!(1))
+            .expect("can't add limit sell")
+            .order_id;
+        let report = book
+            .add_order(Side::Buy, OrderKind::Market, None, dec!(1))
+            .expect("can't add market buy");
+        assert!(report.fills.is_empty());
+        assert_eq!(report.status, OrderStatus::Cancelled);
+
+        assert_eq!(book.phase(), TradingPhase::Halted);
+        assert_eq!(
+            book.add_order(Side::Buy, OrderKind::Limit, Some(dec!(90)), dec!(1))
+                .unwrap_err(),
+            MatcherError::TradingHalted
+        );
+        assert_eq!(
+            book.update_order(sell.clone(), None, None, Some(dec!(91))),
+            Err(MatcherError::TradingHalted)
+        );
+        assert_eq!(book.best_ask(), Some(dec!(90)));
+
+        // taking quantity off goes through like a cancel would
+        let report = book
+            .update_order(sell, Some(dec!(0.5)), None, None)
+            .expect("can't reduce while halted");
+        assert_eq!(report.remaining_quantity, dec!(0.5));
+        assert_eq!(book.sell_volume, dec!(0.5));
+    }
+
+    #[test]
+    pub fn pass_quote_market_order() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+        book.set_rules(InstrumentRules {
+            lot_size: Some(dec!(0.1)),
+            ..Default::default()
+        });
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(2))
+            .expect("can't add limit sell");
+
+        // 1 at 100, then 1.4 of the 1.48 that 150 buys at 101
+        let report = book
+            .add_quote_market_order(Side::Buy, dec!(250), OrderOptions::default())
+            .expect("can't add quote market buy");
+        assert_eq!(report.status, OrderStatus::Executed);
+        assert_eq!(report.filled_quantity, dec!(2.4));
+        assert_eq!(report.leftover_quote, Some(dec!(8.6)));
+        assert_eq!(book.sell_volume, dec!(0.6));
+
+        assert_eq!(
+            book.add_quote_market_order(Side::Buy, dec!(5), OrderOptions::default())
+                .unwrap_err(),
+            MatcherError::QuoteBudgetUnfillable(dec!(5))
+        );
+    }
+
+    #[test]
+    pub fn pass_post_only_amend_rejected_in_place() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell");
+        let buy = book
+            .add_order_with_options(
+                Side::Buy,
+                OrderKind::Limit,
+                Some(dec!(99)),
+                dec!(2),
+                post_only(PostOnly::Reject),
+            )
+            .expect("can't add post-only buy")
+            .order_id;
+
+        let report = book
+            .update_order(buy.clone(), None, None, Some(dec!(101)))
+            .expect("can't amend buy");
+        assert_eq!(report.reject_reason, Some(RejectReason::PostOnlyWouldCross));
+        assert!(report.fills.is_empty());
+
+        let order = book.get_order(buy).expect("original order is gone");
+        assert_eq!(*order.price(), Some(dec!(99)));
+        assert_eq!(book.best_bid(), Some(dec!(99)));
+        assert_eq!(book.buy_volume, dec!(2));
+    }
+
+    #[test]
+    pub fn pass_update_order_priority() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let first = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(3))
+            .expect("can't add limit buy")
+            .order_id;
+        let second = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(3))
+            .expect("can't add limit buy")
+            .order_id;
+
+        // a smaller quantity keeps the front of the queue
+        let report = book
+            .update_order(first.clone(), Some(dec!(2)), None, None)
+            .expect("can't reduce quantity");
+        assert_eq!(report.remaining_quantity, dec!(2));
+        assert_eq!(book.buy_volume, dec!(5));
+        let report = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+        assert_eq!(report.fills[0].maker_order_id, first);
+
+        // a larger quantity goes to the back
+        book.update_order(first.clone(), Some(dec!(4)), None, None)
+            .expect("can't increase quantity");
+        assert_eq!(book.buy_volume, dec!(7));
+        let report = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+        assert_eq!(report.fills[0].maker_order_id, second);
+    }
+
+    #[test]
+    pub fn pass_update_order_reprices_and_matches() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(101)), dec!(1))
+            .expect("can't add limit sell")
+            .order_id;
+        let buy = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(99)), dec!(2))
+            .expect("can't add limit buy")
+            .order_id;
+
+        let report = book
+            .update_order(buy.clone(), None, None, Some(dec!(101)))
+            .expect("can't reprice buy");
+        assert_eq!(report.fills.len(), 1);
+        assert_eq!(report.fills[0].maker_order_id, sell);
+        assert_eq!(report.status, OrderStatus::PartiallyExecuted);
+        assert_eq!(report.price, Some(dec!(101)));
+
+        let depth = book.depth(5);
+        assert_eq!(depth.bids.len(), 1);
+        assert_eq!(depth.bids[0].price, dec!(101));
+        assert_eq!(depth.bids[0].quantity, dec!(1));
+        assert_eq!(book.buy_volume, dec!(1));
+
+        // the cancelled remainder of a market order is not amendable
+        let market = book
+            .add_order(Side::Sell, OrderKind::Market, None, dec!(2))
+            .expect("can't add market sell")
+            .order_id;
+        assert_eq!(
+            book.update_order(market.clone(), Some(dec!(1)), None, None),
+            Err(MatcherError::OrderAlreadyCancelled(market))
+        );
+    }
+
+    #[test]
+    pub fn pass_mass_cancel() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let mut add = |side, price, owner| {
+            book.add_order_with_options(
+                side,
+                OrderKind::Limit,
+                Some(price),
+                dec!(1),
+                owned(owner, SelfTradePrevention::CancelNewest),
+            )
+            .expect("can't add limit order")
+            .order_id
+        };
+        let low_bid = add(Side::Buy, dec!(97), "alice");
+        let high_bid = add(Side::Buy, dec!(99), "alice");
+        let other_bid = add(Side::Buy, dec!(99), "bob");
+        let ask = add(Side::Sell, dec!(101), "alice");
+
+        let cancelled = book.mass_cancel(&CancelFilter {
+            side: Some(Side::Buy),
+            owner: Some(String::from("alice")),
+            min_price: Some(dec!(98)),
+            ..Default::default()
+        });
+        assert_eq!(cancelled, vec![high_bid.clone()]);
+        assert_eq!(book.buy_volume, dec!(2));
+        assert!(book.get_order(high_bid).is_none());
+
+        let mut cancelled = book.mass_cancel(&CancelFilter::default());
+        cancelled.sort();
+        let mut expected = vec![low_bid, other_bid, ask];
+        expected.sort();
+        assert_eq!(cancelled, expected);
+        assert_eq!(book.buy_volume, dec!(0));
+        assert_eq!(book.sell_volume, dec!(0));
+        assert!(book.depth(5).bids.is_empty());
+    }
+
+    #[test]
+    pub fn pass_order_history() {
+        let pair_id = String::from("ETHINC");
+        let mut book = OrderBook::new(pair_id, dec!(100), 8);
+
+        let buy = book
+            .add_order(Side::Buy, OrderKind::Limit, Some(dec!(100)), dec!(3))
+            .expect("can't add limit buy")
+            .order_id;
+        book.add_order(Side::Sell, OrderKind::Limit, Some(dec!(100)), dec!(1))
+            .expect("can't add limit sell");
+        book.update_order(buy.clone(), Some(dec!(1)), None, None)
+            .expect("can't reduce quantity");
+        book.add_order(Side::Sell, OrderKind::Market, None, dec!(2))
+            .expect("can't add market sell");
+
+        // the filled order left the book but its history did not
+        assert!(book.get_order(buy.clone()).is_none());
+        let history = book.order_history(buy).expect("no history");
+        let kinds: Vec<OrderEventKind> = history.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                OrderEventKind::Accepted,
+                OrderEventKind::PartiallyFilled,
+                OrderEventKind::Amended,
+                OrderEventKind::Filled,
+            ]
+        );
+        let quantities: Vec<Decimal> = history.iter().map(|event| event.quantity).collect();
+        assert_eq!(quantities, vec![dec!(3), dec!(2), dec!(1), dec!(0)]);
+        assert_eq!(history[3].traded_quantity, dec!(1));
+        assert_eq!(history[3].status, OrderStatus::Executed);
+
+        let sell = book
+            .add_order(Side::Sell, OrderKind::Limit, Some(dec!(105)), dec!(1))
+            .expect("can't add limit sell")
+            .order_id;
+        book.cancel_order(sell.clone()).expect("can't cancel sell");
+        let history = book.order_history(sell).expect("no history");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].kind, OrderEventKind::Cancelled);
+        assert_eq!(history[1].status, OrderStatus::Cancelled);
     }
 }
@@ -0,0 +1,98 @@
+use rust_decimal::Decimal;
+
+use super::order::{Order, OrderStatus, Side};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub trade_id: String,
+    pub maker_order_id: String,
+    pub taker_order_id: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    // side of the incoming order that took liquidity
+    pub aggressor_side: Side,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    // a post-only order would have taken liquidity
+    PostOnlyWouldCross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    pub order_id: String,
+    pub status: OrderStatus,
+    // limit price the order ended up with, which a post-only reprice may have moved
+    pub price: Option<Decimal>,
+    pub filled_quantity: Decimal,
+    pub remaining_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+    pub fills: Vec<Fill>,
+    pub reject_reason: Option<RejectReason>,
+    // quote a quote-denominated market order did not spend
+    pub leftover_quote: Option<Decimal>,
+    // stop orders this order's trades triggered, in the order they were processed
+    pub triggered: Vec<ExecutionReport>,
+}
+
+impl ExecutionReport {
+    pub fn new(order: &Order, fills: Vec<Fill>) -> ExecutionReport {
+        let filled_quantity: Decimal = fills.iter().map(|fill| fill.quantity).sum();
+
+        let average_price = if fills.is_empty() {
+            None
+        } else {
+            let notional: Decimal = fills.iter().map(|fill| fill.price * fill.quantity).sum();
+            Some(notional / filled_quantity)
+        };
+
+        ExecutionReport {
+            order_id: order.id().clone(),
+            status: *order.status(),
+            price: *order.price(),
+            filled_quantity,
+            remaining_quantity: order.quantity(),
+            average_price,
+            fills,
+            reject_reason: None,
+            leftover_quote: order.quote_budget(),
+            triggered: Vec::new(),
+        }
+    }
+
+    pub fn rejected(order: &Order, reason: RejectReason) -> ExecutionReport {
+        ExecutionReport {
+            reject_reason: Some(reason),
+            ..ExecutionReport::new(order, Vec::new())
+        }
+    }
+}
+
+// a pegged order the book moved after the top of book changed, with whatever the move matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct PegAmend {
+    pub order_id: String,
+    pub old_price: Decimal,
+    pub new_price: Decimal,
+    pub report: ExecutionReport,
+}
+
+// reports for both legs of a one-cancels-other group, in submission order
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoReport {
+    pub group_id: String,
+    pub first: ExecutionReport,
+    pub second: ExecutionReport,
+}
+
+// outcome of ending an auction, every trade at the single uncross price
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncrossReport {
+    pub price: Option<Decimal>,
+    pub volume: Decimal,
+    pub fills: Vec<Fill>,
+    // stop orders the uncross price triggered
+    pub triggered: Vec<ExecutionReport>,
+}
@@ -0,0 +1,181 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use super::order::Side;
+use crate::error::MatcherError;
+
+// instrument rules every order entering or amended on a pair is checked against, nothing is
+// enforced until a rule is set
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct InstrumentRules {
+    pub tick_size: Option<Decimal>,
+    // (price from which the tick applies, tick) in ascending price order, prices below the
+    // first band use tick_size
+    pub tick_table: Vec<(Decimal, Decimal)>,
+    // quantities must be a whole number of lots
+    pub lot_size: Option<Decimal>,
+    pub min_quantity: Option<Decimal>,
+    pub max_quantity: Option<Decimal>,
+    // smallest price times quantity an order may carry
+    pub min_notional: Option<Decimal>,
+}
+
+impl InstrumentRules {
+    pub fn is_valid(&self) -> bool {
+        let positive = |value: Option<Decimal>| value.is_none_or(|value| value > Decimal::ZERO);
+        let bounds_ordered = match (self.min_quantity, self.max_quantity) {
+            (Some(min_quantity), Some(max_quantity)) => min_quantity <= max_quantity,
+            _ => true,
+        };
+
+        positive(self.tick_size)
+            && self
+                .tick_table
+                .iter()
+                .all(|(_, tick)| *tick > Decimal::ZERO)
+            && self
+                .tick_table
+                .windows(2)
+                .all(|bands| bands[0].0 < bands[1].0)
+            && positive(self.lot_size)
+            && positive(self.min_quantity)
+            && positive(self.max_quantity)
+            && positive(self.min_notional)
+            && bounds_ordered
+    }
+
+    // tick that applies at the given price, if any
+    pub fn tick_size(&self, price: Decimal) -> Option<Decimal> {
+        self.tick_table
+            .iter()
+            .take_while(|(from, _)| *from <= price)
+            .last()
+            .map_or(self.tick_size, |(_, tick)| Some(*tick))
+    }
+
+    // puts a price the book worked out itself on the tick, buys round down and sells up
+    pub fn round_to_tick(&self, price: Decimal, side: Side) -> Decimal {
+        let Some(tick_size) = self.tick_size(price) else {
+            return price;
+        };
+        let ticks = price / tick_size;
+        match side {
+            Side::Buy => ticks.floor() * tick_size,
+            Side::Sell => ticks.ceil() * tick_size,
+        }
+    }
+
+    // largest tradeable quantity not above the given one, whole lots at the quantity scale
+    pub fn round_to_lot(&self, quantity: Decimal, quantity_scale: u32) -> Decimal {
+        let quantity = quantity.round_dp_with_strategy(quantity_scale, RoundingStrategy::ToZero);
+        match self.lot_size {
+            Some(lot_size) => (quantity / lot_size).floor() * lot_size,
+            None => quantity,
+        }
+    }
+
+    pub fn check_price(&self, price: Decimal) -> Result<(), MatcherError> {
+        match self.tick_size(price) {
+            Some(tick_size) if !(price % tick_size).is_zero() => {
+                Err(MatcherError::PriceNotOnTick { price, tick_size })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_lot(&self, quantity: Decimal) -> Result<(), MatcherError> {
+        match self.lot_size {
+            Some(lot_size) if !(quantity % lot_size).is_zero() => {
+                Err(MatcherError::QuantityNotOnLot { lot_size })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_quantity(&self, quantity: Decimal) -> Result<(), MatcherError> {
+        self.check_lot(quantity)?;
+
+        if let Some(min_quantity) = self.min_quantity.filter(|min| quantity < *min) {
+            return Err(MatcherError::QuantityBelowMinimum(min_quantity));
+        }
+        if let Some(max_quantity) = self.max_quantity.filter(|max| quantity > *max) {
+            return Err(MatcherError::QuantityAboveMaximum(max_quantity));
+        }
+        Ok(())
+    }
+
+    pub fn check_notional(&self, price: Decimal, quantity: Decimal) -> Result<(), MatcherError> {
+        match self.min_notional {
+            Some(min_notional) if price * quantity < min_notional => {
+                Err(MatcherError::NotionalBelowMinimum(min_notional))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    pub fn pass_instrument_rules() {
+        let rules = InstrumentRules {
+            tick_size: Some(dec!(0.01)),
+            tick_table: vec![(dec!(10), dec!(0.05)), (dec!(100), dec!(0.5))],
+            lot_size: Some(dec!(0.1)),
+            min_quantity: Some(dec!(1)),
+            max_quantity: Some(dec!(100)),
+            min_notional: Some(dec!(50)),
+        };
+        assert!(rules.is_valid());
+
+        assert_eq!(rules.tick_size(dec!(9.99)), Some(dec!(0.01)));
+        assert_eq!(rules.tick_size(dec!(10)), Some(dec!(0.05)));
+        assert_eq!(rules.tick_size(dec!(250)), Some(dec!(0.5)));
+
+        assert!(rules.check_price(dec!(9.99)).is_ok());
+        assert_eq!(rules.round_to_tick(dec!(9.995), Side::Buy), dec!(9.99));
+        assert_eq!(rules.round_to_tick(dec!(100.3), Side::Sell), dec!(100.5));
+        assert_eq!(
+            rules.check_price(dec!(100.2)),
+            Err(MatcherError::PriceNotOnTick {
+                price: dec!(100.2),
+                tick_size: dec!(0.5)
+            })
+        );
+
+        assert_eq!(rules.round_to_lot(dec!(2.5678), 2), dec!(2.5));
+        assert!(rules.check_quantity(dec!(2.5)).is_ok());
+        assert_eq!(
+            rules.check_quantity(dec!(2.55)),
+            Err(MatcherError::QuantityNotOnLot {
+                lot_size: dec!(0.1)
+            })
+        );
+        assert_eq!(
+            rules.check_quantity(dec!(0.5)),
+            Err(MatcherError::QuantityBelowMinimum(dec!(1)))
+        );
+        assert_eq!(
+            rules.check_quantity(dec!(100.1)),
+            Err(MatcherError::QuantityAboveMaximum(dec!(100)))
+        );
+        assert_eq!(
+            rules.check_notional(dec!(10), dec!(4.9)),
+            Err(MatcherError::NotionalBelowMinimum(dec!(50)))
+        );
+
+        let rules = InstrumentRules {
+            min_quantity: Some(dec!(10)),
+            max_quantity: Some(dec!(1)),
+            ..Default::default()
+        };
+        assert!(!rules.is_valid());
+
+        // no tick configured, any price goes
+        assert!(InstrumentRules::default()
+            .check_price(dec!(0.00001234))
+            .is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use rust_decimal::Decimal;
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ticker {
+    pub last_price: Decimal,
+    pub last_size: Decimal,
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub high_24h: Decimal,
+    pub low_24h: Decimal,
+    pub volume_24h: Decimal,
+    pub change_24h: Decimal,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TradePrint {
+    timestamp: i64,
+    price: Decimal,
+    quantity: Decimal,
+}
+
+// rolling 24h window of trade prints backing the ticker statistics
+#[derive(Debug)]
+pub struct TradeWindow {
+    prints: VecDeque<TradePrint>,
+    // last price traded before the window, the reference for the 24h change
+    open_price: Decimal,
+}
+
+impl TradeWindow {
+    pub fn new(listing_price: Decimal) -> TradeWindow {
+        TradeWindow {
+            prints: VecDeque::new(),
+            open_price: listing_price,
+        }
+    }
+
+    pub fn record(&mut self, timestamp: i64, price: Decimal, quantity: Decimal) {
+        while let Some(print) = self.prints.front() {
+            if print.timestamp > timestamp - DAY_SECONDS {
+                break;
+            }
+            self.open_price = print.price;
+            self.prints.pop_front();
+        }
+
+        self.prints.push_back(TradePrint {
+            timestamp,
+            price,
+            quantity,
+        });
+    }
+
+    pub fn ticker(
+        &self,
+        now: i64,
+        last_price: Decimal,
+        last_size: Decimal,
+        best_bid: Option<Decimal>,
+        best_ask: Option<Decimal>,
+    ) -> Ticker {
+        let mut open_price = self.open_price;
+        let mut high_24h = None;
+        let mut low_24h = None;
+        let mut volume_24h = Decimal::ZERO;
+
+        for print in self.prints.iter() {
+            if print.timestamp <= now - DAY_SECONDS {
+                open_price = print.price;
+                continue;
+            }
+            high_24h = Some(high_24h.map_or(print.price, |high: Decimal| high.max(print.price)));
+            low_24h = Some(low_24h.map_or(print.price, |low: Decimal| low.min(print.price)));
+            volume_24h += print.quantity;
+        }
+
+        Ticker {
+            last_price,
+            last_size,
+            best_bid,
+            best_ask,
+            high_24h: high_24h.unwrap_or(last_price),
+            low_24h: low_24h.unwrap_or(last_price),
+            volume_24h,
+            change_24h: last_price - open_price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    pub fn pass_rolling_window() {
+        let mut window = TradeWindow::new(dec!(100));
+        let now = 10 * DAY_SECONDS;
+
+        window.record(now - DAY_SECONDS - 5, dec!(90), dec!(1));
+        window.record(now - 10, dec!(110), dec!(2));
+        window.record(now - 5, dec!(105), dec!(3));
+
+        let ticker = window.ticker(now, dec!(105), dec!(3), None, None);
+
+        // the print older than 24h only sets the reference price
+        assert_eq!(ticker.high_24h, dec!(110));
+        assert_eq!(ticker.low_24h, dec!(105));
+        assert_eq!(ticker.volume_24h, dec!(5));
+        assert_eq!(ticker.change_24h, dec!(15));
+    }
+}